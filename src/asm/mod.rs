@@ -0,0 +1,138 @@
+use anyhow::{anyhow, bail};
+use std::collections::HashMap;
+
+use crate::opcode;
+
+// assembles `.easm` sources.
+//
+// - one or more instructions per line, mnemonics are case insensitive: `PUSH1 0x01 ADD`
+// - a bare literal `0x2a` is pushed with the smallest PUSHn
+// - `name:` places a JUMPDEST, `@name` pushes its offset with PUSH2
+// - `//` and `;` start a comment
+pub(crate) fn assemble(source: &str) -> anyhow::Result<Vec<u8>> {
+    let mut items = vec![];
+    for (i, line) in source.lines().enumerate() {
+        let line = line.split("//").next().unwrap_or_default();
+        let line = line.split(';').next().unwrap_or_default();
+        for token in line.split_whitespace() {
+            items.push((i + 1, token));
+        }
+    }
+
+    // first pass computes label offsets, second pass emits.
+    let mut labels = HashMap::new();
+    let mut offset = 0;
+    let mut i = 0;
+    while i < items.len() {
+        let (line, token) = items[i];
+        if let Some(label) = token.strip_suffix(':') {
+            if labels.insert(label, offset).is_some() {
+                bail!("line {}: duplicate label {:?}", line, label);
+            }
+            offset += 1;
+        } else if token.starts_with('@') {
+            offset += 3;
+        } else if token.starts_with("0x") {
+            offset += 1 + literal(token, line)?.len();
+        } else {
+            let op = opcode::from_name(token).ok_or(anyhow!("line {}: unknown opcode {:?}", line, token))?;
+            offset += 1 + opcode::immediate_size(op);
+            if opcode::immediate_size(op) > 0 {
+                i += 1;
+            }
+        }
+        i += 1;
+    }
+
+    let mut code = vec![];
+    let mut i = 0;
+    while i < items.len() {
+        let (line, token) = items[i];
+        if token.ends_with(':') {
+            code.push(opcode::from_name("jumpdest").unwrap_or_default());
+        } else if let Some(label) = token.strip_prefix('@') {
+            let target: usize = *labels.get(label).ok_or(anyhow!("line {}: unknown label {:?}", line, label))?;
+            if target > 0xffff {
+                bail!("line {}: label {:?} is out of PUSH2 range", line, label);
+            }
+            code.push(opcode::from_name("push2").unwrap_or_default());
+            code.extend_from_slice(&(target as u16).to_be_bytes());
+        } else if token.starts_with("0x") {
+            let bytes = literal(token, line)?;
+            code.push(0x5f + bytes.len() as u8);
+            code.extend(bytes);
+        } else {
+            let op = opcode::from_name(token).ok_or(anyhow!("line {}: unknown opcode {:?}", line, token))?;
+            code.push(op);
+            let size = opcode::immediate_size(op);
+            if size > 0 {
+                i += 1;
+                let (line, token) = *items
+                    .get(i)
+                    .ok_or(anyhow!("line {}: {} needs an immediate", line, token))?;
+                let bytes = literal(token, line)?;
+                if bytes.len() > size {
+                    bail!("line {}: {} does not fit in {} bytes", line, token, size);
+                }
+                code.resize(code.len() + size - bytes.len(), 0);
+                code.extend(bytes);
+            }
+        }
+        i += 1;
+    }
+    Ok(code)
+}
+
+// big endian bytes of a literal without leading zero bytes, at least one byte.
+fn literal(token: &str, line: usize) -> anyhow::Result<Vec<u8>> {
+    let digits = token.trim_start_matches("0x").trim_start_matches('0');
+    let digits = if digits.len() % 2 == 1 {
+        format!("0{}", digits)
+    } else {
+        digits.to_owned()
+    };
+    let bytes = hex::decode(&digits).map_err(|err| anyhow!("line {}: invalid literal {:?}: {}", line, token, err))?;
+    if bytes.len() > 32 {
+        bail!("line {}: literal {:?} is longer than 32 bytes", line, token);
+    }
+    if bytes.is_empty() {
+        return Ok(vec![0]);
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assembles_mnemonics_and_literals() {
+        let code = assemble("PUSH1 0x01 push1 0x02 ADD // sum\n0x2a 0x0100 ; smallest push\nstop").unwrap();
+        assert_eq!(code, vec![0x60, 0x01, 0x60, 0x02, 0x01, 0x60, 0x2a, 0x61, 0x01, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn pads_explicit_push_immediates() {
+        assert_eq!(assemble("PUSH4 0x01").unwrap(), vec![0x63, 0x00, 0x00, 0x00, 0x01]);
+        assert_eq!(assemble("0x00").unwrap(), vec![0x60, 0x00]);
+        assert!(assemble("PUSH1 0x0100").is_err());
+        assert!(assemble("PUSH1").is_err());
+    }
+
+    #[test]
+    fn resolves_labels_forward_and_backward() {
+        let code = assemble("@end JUMP\nloop:\n@loop JUMP\nend: STOP").unwrap();
+        assert_eq!(
+            code,
+            vec![0x61, 0x00, 0x09, 0x56, 0x5b, 0x61, 0x00, 0x04, 0x56, 0x5b, 0x00]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_and_duplicate_names() {
+        assert!(assemble("FOO").is_err());
+        assert!(assemble("@missing JUMP").is_err());
+        assert!(assemble("a:\na:").is_err());
+        assert!(assemble(&format!("0x1{}", "00".repeat(32))).is_err());
+    }
+}
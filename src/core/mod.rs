@@ -1,37 +1,506 @@
+use anyhow::{anyhow, bail};
 use primitive_types::{H160, H256, U256};
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
+#[derive(Clone)]
 pub(crate) struct Input {
     pub id: String,
     pub code: Vec<u8>,
     pub value: u128,
     pub calldata: Vec<u8>,
     pub accounts: Vec<AccountDeseriarizable>,
+    pub source_map: BTreeMap<usize, SourceLocation>,
+    pub env: Env,
+    pub expect: Option<ExpectConfig>,
+    // named accounts and the addresses they resolve to.
+    pub names: BTreeMap<String, H160>,
+    // path of the snapshot other accounts are read from.
+    pub snapshot: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+// transaction and block environment a test is executed in.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Env {
+    pub caller: H160,
+    // address the test code is deployed at.
+    pub address: H160,
+    pub origin: H160,
+    pub gas_limit: u64,
+    pub gas_price: U256,
+    pub block_number: U256,
+    pub block_coinbase: H160,
+    pub block_timestamp: U256,
+    pub block_difficulty: U256,
+    pub block_gas_limit: U256,
+    pub block_base_fee_per_gas: U256,
+    pub chain_id: U256,
+    pub fork: String,
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Env {
+            caller: H160::from_str("0xf000000000000000000000000000000000000000").unwrap(),
+            address: H160::from_str("0x1000000000000000000000000000000000000000").unwrap(),
+            origin: H160::default(),
+            gas_limit: u64::MAX,
+            gas_price: U256::zero(),
+            block_number: U256::zero(),
+            block_coinbase: H160::default(),
+            block_timestamp: U256::zero(),
+            block_difficulty: U256::zero(),
+            block_gas_limit: U256::zero(),
+            block_base_fee_per_gas: U256::zero(),
+            chain_id: U256::one(),
+            fork: "london".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct SourceLocation {
+    pub file: String,
+    pub line: usize,
+}
+
+impl std::fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TraceStep {
+    pub address: H160,
+    pub pc: usize,
+    pub opcode: u8,
+    // whether the step runs the test's own code, i.e. the pc is covered by its source map.
+    pub target: bool,
+}
+
+// testcase.json. everything but `name` is a default shared by the tests of the group.
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub(crate) struct TestGroupConfig {
     pub name: String,
+    // named accounts, usable as `${name}` here and in every config of the group.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub names: Vec<String>,
+    // applied before the env of each test.
+    #[serde(default, skip_serializing_if = "EnvConfig::is_empty")]
+    pub env: EnvConfig,
+    // deployed in every test of the group.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accounts: Vec<AccountDeseriarizable>,
+    // deployed only in tests listing them in `fixtures`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fixtures: BTreeMap<String, AccountDeseriarizable>,
+    #[serde(default, skip_serializing_if = "AbortConfig::is_default")]
+    pub abort: AbortConfig,
+    // move target: `aptos` (default), `sui`, or a handlebars file relative to testcase.json.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub template: String,
+    #[serde(default, skip_serializing_if = "SplitConfig::is_default")]
+    pub split: SplitConfig,
+    // canonical paths of the code files of `accounts` and `fixtures`. they sit next to the
+    // stateless tests but are not tests themselves.
+    #[serde(skip)]
+    pub code_files: BTreeSet<PathBuf>,
+}
+
+impl TestGroupConfig {
+    pub fn is_code_file(&self, path: &Path) -> bool {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+        self.code_files.contains(&path)
+    }
+}
+
+// limits of a move test module. a group exceeding them is split into `<group>_tests_N` modules.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub(crate) struct SplitConfig {
+    #[serde(default)]
+    pub max_tests: Option<usize>,
+    // size of the rendered module in bytes.
+    #[serde(default)]
+    pub max_bytes: Option<usize>,
+}
+
+impl SplitConfig {
+    pub fn is_default(&self) -> bool {
+        self == &SplitConfig::default()
+    }
+}
+
+// how the move vm aborts on tests that do not succeed.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub(crate) struct AbortConfig {
+    // exit reason, as named by `exit_name`, to abort code. `*` applies to unlisted reasons.
+    #[serde(default)]
+    pub codes: BTreeMap<String, u64>,
+    // module raising the abort, e.g. `pocvm::vm`.
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+impl AbortConfig {
+    pub fn is_default(&self) -> bool {
+        self == &AbortConfig::default()
+    }
+
+    // the `#[expected_failure]` attribute of a test exiting with `result`. without a code for
+    // the reason, any abort is expected.
+    pub fn expected_failure(&self, result: &evm::ExitReason) -> Option<String> {
+        if matches!(result, evm::ExitReason::Succeed(_)) {
+            return None;
+        }
+        let code = self.codes.get(&exit_name(result)).or(self.codes.get("*"));
+        let attribute = match (code, &self.location) {
+            (Some(code), Some(location)) => {
+                format!("#[expected_failure(abort_code = {}, location = {})]", code, location)
+            }
+            (Some(code), None) => format!("#[expected_failure(abort_code = {})]", code),
+            (None, _) => "#[expected_failure]".to_owned(),
+        };
+        Some(attribute)
+    }
+}
+
+// resources/package.json, the Move.toml of the package written with `--package`. addresses and
+// dependencies are added to, or replace by name, the defaults of the target.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub(crate) struct PackageConfig {
+    // `evm_tests` by default.
+    #[serde(default)]
+    pub name: String,
+    // `aptos` (default) or `sui`.
+    #[serde(default)]
+    pub target: String,
+    #[serde(default)]
+    pub addresses: BTreeMap<String, String>,
+    #[serde(default)]
+    pub dev_addresses: BTreeMap<String, String>,
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, MoveDependency>,
 }
 
-#[derive(Deserialize, Debug)]
+// a local path, relative to the package directory, or a git repository.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub(crate) struct MoveDependency {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subdir: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 pub(crate) struct StateConfig {
     pub id: String,
+    // named accounts, usable as `${name}` in any string of the config.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub names: Vec<String>,
+    // source file next to state.json. not needed when the code is given inline.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub filename: String,
+    // defaults to the extension of `filename`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub filetype: String,
+    // inline code as hex.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    // inline code as easm assembly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asm: Option<String>,
+    #[serde(default)]
     pub value: u128,
+    #[serde(default)]
+    pub calldata: String,
+    // group fixtures deployed in this test.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fixtures: Vec<String>,
+    // replace group and fixture accounts with the same address, or are added.
+    #[serde(default)]
+    pub accounts: Vec<AccountDeseriarizable>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cases: Vec<CaseConfig>,
+    // alloc json file or directory of `<address>.json` files, relative to state.json. accounts
+    // not listed in `accounts` are read from it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<String>,
+    #[serde(default, skip_serializing_if = "HuffConfig::is_default")]
+    pub huff: HuffConfig,
+    #[serde(default, skip_serializing_if = "EnvConfig::is_empty")]
+    pub env: EnvConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expect: Option<ExpectConfig>,
+}
+
+// one row of a parameterized test. shares the code and accounts of its state.json and becomes
+// the test `<id>_<case id>`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct CaseConfig {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calldata: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<u128>,
+    // replace the base account with the same address, or are added.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accounts: Vec<AccountDeseriarizable>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expect: Option<ExpectConfig>,
+}
+
+// overrides of the default `Env`. addresses are hex strings like account addresses.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub(crate) struct EnvConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caller: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_limit: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_price: Option<u128>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u128>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coinbase: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u128>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<u128>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_gas_limit: Option<u128>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_fee: Option<u128>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u128>,
+    // one of frontier, istanbul, berlin, london.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork: Option<String>,
+}
+
+impl EnvConfig {
+    pub fn is_empty(&self) -> bool {
+        self == &EnvConfig::default()
+    }
+
+    pub fn apply(&self, env: &mut Env) -> anyhow::Result<()> {
+        if let Some(caller) = &self.caller {
+            env.caller = parse_address(caller)?;
+        }
+        if let Some(address) = &self.address {
+            env.address = parse_address(address)?;
+        }
+        if let Some(origin) = &self.origin {
+            env.origin = parse_address(origin)?;
+        }
+        if let Some(gas_limit) = self.gas_limit {
+            env.gas_limit = gas_limit;
+        }
+        if let Some(gas_price) = self.gas_price {
+            env.gas_price = U256::from(gas_price);
+        }
+        if let Some(block_number) = self.block_number {
+            env.block_number = U256::from(block_number);
+        }
+        if let Some(coinbase) = &self.coinbase {
+            env.block_coinbase = parse_address(coinbase)?;
+        }
+        if let Some(timestamp) = self.timestamp {
+            env.block_timestamp = U256::from(timestamp);
+        }
+        if let Some(difficulty) = self.difficulty {
+            env.block_difficulty = U256::from(difficulty);
+        }
+        if let Some(block_gas_limit) = self.block_gas_limit {
+            env.block_gas_limit = U256::from(block_gas_limit);
+        }
+        if let Some(base_fee) = self.base_fee {
+            env.block_base_fee_per_gas = U256::from(base_fee);
+        }
+        if let Some(chain_id) = self.chain_id {
+            env.chain_id = U256::from(chain_id);
+        }
+        if let Some(fork) = &self.fork {
+            env.fork = fork.to_owned();
+        }
+        Ok(())
+    }
+}
+
+// expected results. only the listed fields are compared.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub(crate) struct ExpectConfig {
+    // return data as hex.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    // exit reason as named by `exit_name`, e.g. "Returned", "Reverted", "StackUnderflow".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accounts: Vec<ExpectedAccount>,
+    // root of the full post-state with the transaction fees charged, as in state tests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_root: Option<String>,
+    // keccak of the rlp encoded logs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logs_hash: Option<String>,
+}
+
+// calldata, value, accounts and env of a stateless test, read from `<name>.json` next to the
+// source or from `// @key value` header comments.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub(crate) struct StatelessConfig {
+    #[serde(default)]
+    pub names: Vec<String>,
+    #[serde(default)]
     pub calldata: String,
+    #[serde(default)]
+    pub value: u128,
+    #[serde(default)]
+    pub fixtures: Vec<String>,
+    #[serde(default)]
     pub accounts: Vec<AccountDeseriarizable>,
+    #[serde(default)]
+    pub env: EnvConfig,
+    #[serde(default)]
+    pub expect: Option<ExpectConfig>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct ExpectedAccount {
+    pub address: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<u128>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<u128>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub storage: HashMap<String, String>,
+}
+
+impl ExpectConfig {
+    pub fn check(
+        &self,
+        output: &[u8],
+        result: &evm::ExitReason,
+        accounts_output: &[NormalizedAccount],
+    ) -> anyhow::Result<()> {
+        let mut mismatches = vec![];
+        if let Some(expected) = &self.output {
+            if hex::encode(output) != expected.trim_start_matches("0x") {
+                mismatches.push(format!("output 0x{} != 0x{}", hex::encode(output), expected.trim_start_matches("0x")));
+            }
+        }
+        if let Some(expected) = &self.exit {
+            let exit = exit_name(result);
+            if !exit.eq_ignore_ascii_case(expected) {
+                mismatches.push(format!("exit {} != {}", exit, expected));
+            }
+        }
+        for expected in &self.accounts {
+            let address = parse_address(&expected.address)?;
+            let actual = match accounts_output.iter().find(|acct| acct.address == address) {
+                Some(actual) => actual,
+                None => {
+                    mismatches.push(format!("{:?}: not accessed", address));
+                    continue;
+                }
+            };
+            if let Some(balance) = expected.balance {
+                if actual.balance != U256::from(balance) {
+                    mismatches.push(format!("{:?}: balance {} != {}", address, actual.balance, balance));
+                }
+            }
+            if let Some(nonce) = expected.nonce {
+                if actual.nonce != U256::from(nonce) {
+                    mismatches.push(format!("{:?}: nonce {} != {}", address, actual.nonce, nonce));
+                }
+            }
+            if let Some(code) = &expected.code {
+                if hex::encode(&actual.code) != code.trim_start_matches("0x") {
+                    mismatches.push(format!("{:?}: code differs", address));
+                }
+            }
+            for (key, value) in &expected.storage {
+                let key = parse_word(key)?;
+                let value = parse_word(value)?;
+                let actual_value = actual.storage.get(&key).cloned().unwrap_or_default();
+                if actual_value != value {
+                    mismatches.push(format!("{:?}: storage {:?} is {:?}, expected {:?}", address, key, actual_value, value));
+                }
+            }
+        }
+        if !mismatches.is_empty() {
+            bail!("unexpected result:\n  {}", mismatches.join("\n  "));
+        }
+        Ok(())
+    }
+
+    // `state_root` is only computed when `self.state_root` is set.
+    pub fn check_roots(&self, state_root: Option<H256>, logs_hash: H256) -> anyhow::Result<()> {
+        let mut mismatches = vec![];
+        if let (Some(expected), Some(state_root)) = (&self.state_root, state_root) {
+            if state_root != parse_word(expected)? {
+                mismatches.push(format!("state root {:?} != {}", state_root, expected));
+            }
+        }
+        if let Some(expected) = &self.logs_hash {
+            if logs_hash != parse_word(expected)? {
+                mismatches.push(format!("logs hash {:?} != {}", logs_hash, expected));
+            }
+        }
+        if !mismatches.is_empty() {
+            bail!("unexpected result:\n  {}", mismatches.join("\n  "));
+        }
+        Ok(())
+    }
+}
+
+// compiler options for huff sources.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub(crate) struct HuffConfig {
+    // constructor arguments appended to the creation code.
+    #[serde(default)]
+    pub constructor_args: Vec<String>,
+    // macro used instead of MAIN as the runtime entry point.
+    #[serde(default)]
+    pub entry_macro: Option<String>,
+    // `#define constant` overrides, name to hex value.
+    #[serde(default)]
+    pub constants: BTreeMap<String, String>,
+    // extra directories searched by `#include`, relative to the source file.
+    #[serde(default)]
+    pub include_paths: Vec<String>,
+    // use the creation code (constructor + runtime) instead of the runtime code.
+    #[serde(default)]
+    pub creation: bool,
+}
+
+impl HuffConfig {
+    pub fn is_default(&self) -> bool {
+        self == &HuffConfig::default()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub(crate) struct AccountDeseriarizable {
     pub address: String,
     pub balance: u128,
     pub nonce: u128,
+    // hex, or a .huff / .bytecode / .easm file relative to the config.
     pub code: String,
-    pub storage: HashMap<String, String>,
+    pub storage: BTreeMap<String, String>,
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -51,19 +520,39 @@ pub(crate) struct Output {
 
 #[derive(Debug, Clone)]
 pub(crate) struct TestCase {
+    // move identifier of the test, unique within its group.
     pub funcname: String,
+    // the test name as written, e.g. the file stem or `StateConfig.id`.
+    pub id: String,
+    // file defining the test, `<path>:<id>` for tests of a multi-test file.
+    pub source: String,
     pub code: Vec<u8>,
     pub value: u128,
     pub calldata: Vec<u8>,
     pub output: Vec<u8>,
     pub accounts_input: Vec<NormalizedAccount>,
     pub accounts_output: Vec<NormalizedAccount>,
+    // every account before the call, including the test and caller accounts.
+    pub pre_state: Vec<NormalizedAccount>,
     pub result: evm::ExitReason,
+    pub source_map: BTreeMap<usize, SourceLocation>,
+    pub trace: Vec<TraceStep>,
+    pub exit_pc: Option<usize>,
+    pub env: Env,
+    pub names: BTreeMap<String, H160>,
+}
+
+impl TestCase {
+    pub fn exit_location(&self) -> Option<&SourceLocation> {
+        self.exit_pc.and_then(|pc| self.source_map.get(&pc))
+    }
 }
 
 #[derive(Debug, Serialize)]
 pub(crate) struct TestCaseSerializable {
     pub funcname: String,
+    pub id: String,
+    pub source: String,
     pub code: String,
     pub value: U256,
     pub calldata: String,
@@ -71,16 +560,24 @@ pub(crate) struct TestCaseSerializable {
     pub accounts_input: Vec<AccountSeriarizable>,
     pub accounts_output: Vec<AccountSeriarizable>,
     pub result: evm::ExitReason,
+    pub exit_pc: Option<usize>,
+    pub exit_location: Option<String>,
+    pub source_map: BTreeMap<usize, String>,
+    pub env: Env,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub names: BTreeMap<String, H160>,
 }
 
 impl From<&TestCase> for TestCaseSerializable {
     fn from(tc: &TestCase) -> Self {
-        return TestCaseSerializable {
+        TestCaseSerializable {
             funcname: tc.funcname.to_owned(),
-            code: hex::encode(tc.code.to_owned()),
+            id: tc.id.to_owned(),
+            source: tc.source.to_owned(),
+            code: hex::encode(&tc.code),
             value: U256::from(tc.value),
-            calldata: hex::encode(tc.calldata.to_owned()),
-            output: hex::encode(tc.output.to_owned()),
+            calldata: hex::encode(&tc.calldata),
+            output: hex::encode(&tc.output),
             accounts_input: tc.accounts_input.iter().map(|acct| AccountSeriarizable {
                 address: acct.address,
                 balance: acct.balance,
@@ -96,6 +593,11 @@ impl From<&TestCase> for TestCaseSerializable {
                 storage: acct.storage.to_owned(),
             }).collect(), 
             result: tc.result.to_owned(),
+            exit_pc: tc.exit_pc,
+            exit_location: tc.exit_location().map(|loc| loc.to_string()),
+            source_map: tc.source_map.iter().map(|(pc, loc)| (*pc, loc.to_string())).collect(),
+            env: tc.env.to_owned(),
+            names: tc.names.to_owned(),
         }
     }
 }
@@ -109,46 +611,22 @@ pub(crate) struct NormalizedAccount {
     pub storage: BTreeMap<H256, H256>,
 }
 
-impl From<AccountDeseriarizable> for NormalizedAccount {
-    fn from(acct: AccountDeseriarizable) -> Self {
-        let mut btree = BTreeMap::new();
-        for (key, value) in &acct.storage {
-            let key = str_to_H256(&key);
-            let value = str_to_H256(&value);
-            btree.insert(key, value);
-        }
-        let address = str_to_H160(&acct.address);
+// user supplied values may be 0x prefixed or odd length, so this goes through the same parsers as the rest of the configs.
+impl TryFrom<&AccountDeseriarizable> for NormalizedAccount {
+    type Error = anyhow::Error;
 
-        let normal_acct = NormalizedAccount {
-            address,
-            balance: U256::from(acct.balance),
-            nonce: U256::from(acct.nonce),
-            code: hex::decode(&acct.code).unwrap(),
-            storage: btree,
-        };
-
-        normal_acct
-    }
-}
-impl From<&AccountDeseriarizable> for NormalizedAccount {
-    fn from(acct: &AccountDeseriarizable) -> Self {
-        let mut btree = BTreeMap::new();
+    fn try_from(acct: &AccountDeseriarizable) -> anyhow::Result<Self> {
+        let mut storage = BTreeMap::new();
         for (key, value) in &acct.storage {
-            let key = str_to_H256(&key);
-            let value = str_to_H256(&value);
-            btree.insert(key, value);
+            storage.insert(parse_word(key)?, parse_word(value)?);
         }
-        let address = str_to_H160(&acct.address);
-
-        let normal_acct = NormalizedAccount {
-            address,
+        Ok(NormalizedAccount {
+            address: parse_address(&acct.address)?,
             balance: U256::from(acct.balance),
             nonce: U256::from(acct.nonce),
-            code: hex::decode(&acct.code).unwrap(),
-            storage: btree,
-        };
-
-        normal_acct
+            code: decode_data(&acct.code)?,
+            storage,
+        })
     }
 }
 
@@ -168,26 +646,112 @@ pub(crate) enum FileType {
     Huff,
     Solidity,
     Bytecode,
+    Easm,
+}
+
+// hex with or without 0x. shorter values are left padded.
+pub(crate) fn parse_address(src: &str) -> anyhow::Result<H160> {
+    let bytes = decode_hex(src)?;
+    if bytes.len() > 20 {
+        bail!("address {:?} is longer than 20 bytes", src);
+    }
+    let mut word = [0u8; 20];
+    word[20 - bytes.len()..].copy_from_slice(&bytes);
+    Ok(H160::from(&word))
 }
 
-#[allow(non_snake_case)]
-fn str_to_H256(src: &str) -> H256 {
+pub(crate) fn parse_word(src: &str) -> anyhow::Result<H256> {
+    let bytes = decode_hex(src)?;
+    if bytes.len() > 32 {
+        bail!("word {:?} is longer than 32 bytes", src);
+    }
     let mut word = [0u8; 32];
-    let vec: Vec<u8> = hex::decode(&src).unwrap();
-    let length = vec.len();
-    for i in 0..length {
-        word[31 - length + 1 + i] = vec[i];
+    word[32 - bytes.len()..].copy_from_slice(&bytes);
+    Ok(H256::from(&word))
+}
+
+// a hex (0x) or decimal quantity, as in geth dumps and ethereum tests.
+pub(crate) fn parse_u256(value: &str) -> anyhow::Result<U256> {
+    if let Some(value) = value.strip_prefix("0x") {
+        if value.is_empty() {
+            return Ok(U256::zero());
+        }
+        return Ok(U256::from_str_radix(value, 16)?);
     }
-    H256::from(&word)
+    Ok(U256::from_dec_str(value)?)
 }
 
-#[allow(non_snake_case)]
-fn str_to_H160(src: &str) -> H160 {
-    let mut word = [0u8; 20];
-    let vec: Vec<u8> = hex::decode(&src).unwrap();
-    let length = vec.len();
-    for i in 0..length {
-        word[19 - length + 1 + i] = vec[i];
+// calldata or inline code. may span lines, e.g. folded yaml strings.
+pub(crate) fn decode_data(src: &str) -> anyhow::Result<Vec<u8>> {
+    let src: String = src.chars().filter(|c| !c.is_whitespace()).collect();
+    let src = src.trim_start_matches("0x");
+    hex::decode(src).map_err(|err| anyhow!("invalid hex {:?}: {}", src, err))
+}
+
+// hex with or without 0x, odd lengths are left padded.
+pub(crate) fn decode_hex(src: &str) -> anyhow::Result<Vec<u8>> {
+    let src = src.trim_start_matches("0x");
+    let bytes = if src.len() % 2 == 1 {
+        hex::decode(format!("0{}", src))
+    } else {
+        hex::decode(src)
+    };
+    bytes.map_err(|err| anyhow!("invalid hex {:?}: {}", src, err))
+}
+
+// name of the innermost exit reason variant, e.g. "Returned" or "StackUnderflow".
+pub(crate) fn exit_name(reason: &evm::ExitReason) -> String {
+    let name = match reason {
+        evm::ExitReason::Succeed(s) => format!("{:?}", s),
+        evm::ExitReason::Error(e) => format!("{:?}", e),
+        evm::ExitReason::Revert(r) => format!("{:?}", r),
+        evm::ExitReason::Fatal(f) => format!("{:?}", f),
+    };
+    name.split('(').next().unwrap_or_default().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_u256_reads_hex_and_decimal() {
+        assert_eq!(parse_u256("0x").unwrap(), U256::zero());
+        assert_eq!(parse_u256("0x0a").unwrap(), U256::from(10));
+        assert_eq!(parse_u256("0xa").unwrap(), U256::from(10));
+        assert_eq!(parse_u256("100000000000000000000").unwrap(), U256::exp10(20));
+        assert_eq!(parse_u256(&format!("0x{}", "ff".repeat(32))).unwrap(), U256::MAX);
+        assert!(parse_u256("0xzz").is_err());
+        assert!(parse_u256(&format!("0x1{}", "00".repeat(32))).is_err());
+    }
+
+    #[test]
+    fn normalizes_prefixed_and_odd_length_accounts() {
+        let acct = AccountDeseriarizable {
+            address: "0xabc".to_owned(),
+            balance: 1,
+            nonce: 2,
+            code: "0x6001".to_owned(),
+            storage: BTreeMap::from([("0x1".to_owned(), "abc".to_owned())]),
+        };
+        let normal = NormalizedAccount::try_from(&acct).unwrap();
+        assert_eq!(normal.address, H160::from_low_u64_be(0xabc));
+        assert_eq!(normal.code, vec![0x60, 0x01]);
+        assert_eq!(
+            normal.storage.get(&H256::from_low_u64_be(1)),
+            Some(&H256::from_low_u64_be(0xabc))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_accounts() {
+        let acct = AccountDeseriarizable {
+            address: "01".to_owned(),
+            balance: 0,
+            nonce: 0,
+            code: "600".to_owned(),
+            storage: BTreeMap::new(),
+        };
+        assert!(NormalizedAccount::try_from(&acct).is_err());
     }
-    H160::from(&word)
 }
@@ -1,11 +1,13 @@
+use anyhow::{bail, Context};
 use evm::backend::Backend;
 use std::collections::BTreeMap;
-use std::str::FromStr;
 
-use crate::core::{AccountDeseriarizable, NormalizedAccount};
-use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use crate::core::{AccountDeseriarizable, Env, NormalizedAccount, TraceStep};
+use crate::executor::snapshot::{Snapshot, SnapshotBackend};
+use evm::backend::{Apply, Log, MemoryAccount, MemoryBackend, MemoryVicinity};
 use evm::executor::stack::{MemoryStackState, StackExecutor, StackState, StackSubstateMetadata};
 use evm::Config;
+use evm_runtime::tracing::{Event, EventListener};
 use primitive_types::{H160, H256, U256};
 
 #[derive(Debug)]
@@ -17,6 +19,49 @@ pub(crate) struct ExecutionResult {
     pub accounts_input: Vec<NormalizedAccount>,
     pub accounts_output: Vec<NormalizedAccount>,
     pub result: evm::ExitReason,
+    pub trace: Vec<TraceStep>,
+    pub exit_pc: Option<usize>,
+    // every account before and after the call, including the test and caller accounts.
+    pub pre_state: Vec<NormalizedAccount>,
+    pub post_state: Vec<NormalizedAccount>,
+    pub logs: Vec<Log>,
+    // gas of the transaction after refunds. fees are not charged, see `charge_fees`.
+    pub used_gas: u64,
+}
+
+struct StepListener {
+    target: H160,
+    steps: Vec<TraceStep>,
+}
+
+impl EventListener for StepListener {
+    fn event(&mut self, event: Event) {
+        if let Event::Step {
+            context,
+            opcode,
+            position: Ok(pc),
+            ..
+        } = event
+        {
+            self.steps.push(TraceStep {
+                address: context.address,
+                pc: *pc,
+                opcode: opcode.0,
+                target: context.address == self.target,
+            });
+        }
+    }
+}
+
+pub(crate) fn fork_config(fork: &str) -> anyhow::Result<Config> {
+    let config = match fork.to_ascii_lowercase().as_str() {
+        "frontier" => Config::frontier(),
+        "istanbul" => Config::istanbul(),
+        "berlin" => Config::berlin(),
+        "london" => Config::london(),
+        _ => bail!("unsupported fork {:?}", fork),
+    };
+    Ok(config)
 }
 
 pub(crate) fn execute(
@@ -25,24 +70,26 @@ pub(crate) fn execute(
     calldata: &[u8],
     balance: u128,
     accounts: &[AccountDeseriarizable],
+    env: &Env,
+    snapshot: Option<&str>,
 ) -> anyhow::Result<ExecutionResult> {
-    let config = Config::london();
+    let config = fork_config(&env.fork)?;
 
     let vicinity = MemoryVicinity {
-        gas_price: U256::zero(),
-        origin: H160::default(),
+        gas_price: env.gas_price,
+        origin: env.origin,
         block_hashes: Vec::new(),
-        block_number: Default::default(),
-        block_coinbase: Default::default(),
-        block_timestamp: Default::default(),
-        block_difficulty: Default::default(),
-        block_gas_limit: Default::default(),
-        chain_id: U256::one(),
-        block_base_fee_per_gas: U256::zero(),
+        block_number: env.block_number,
+        block_coinbase: env.block_coinbase,
+        block_timestamp: env.block_timestamp,
+        block_difficulty: env.block_difficulty,
+        block_gas_limit: env.block_gas_limit,
+        chain_id: env.chain_id,
+        block_base_fee_per_gas: env.block_base_fee_per_gas,
     };
 
-    let caller_address = H160::from_str("0xf000000000000000000000000000000000000000")?;
-    let dest_address = H160::from_str("0x1000000000000000000000000000000000000000")?;
+    let caller_address = env.caller;
+    let dest_address = env.address;
 
     let mut state = BTreeMap::new();
     state.insert(
@@ -66,7 +113,12 @@ pub(crate) fn execute(
 
     let mut accounts_input = vec![];
     for acct in accounts {
-        let normal_acct: NormalizedAccount = acct.into();
+        let mut normal_acct = NormalizedAccount::try_from(acct)
+            .with_context(|| format!("invalid account {}", acct.address))?;
+        // an account listed at the test address keeps the test code unless it has its own.
+        if normal_acct.address == dest_address && normal_acct.code.is_empty() {
+            normal_acct.code = Vec::from(code);
+        }
         state.insert(
             normal_acct.address,
             MemoryAccount {
@@ -79,22 +131,45 @@ pub(crate) fn execute(
         accounts_input.push(normal_acct);
     }
 
-    let backend = MemoryBackend::new(&vicinity, state);
+    let mut pre_state: BTreeMap<H160, NormalizedAccount> = state
+        .iter()
+        .map(|(address, acct)| {
+            let normal_acct = NormalizedAccount {
+                address: *address,
+                balance: acct.balance,
+                nonce: acct.nonce,
+                code: acct.code.clone(),
+                storage: acct.storage.clone(),
+            };
+            (*address, normal_acct)
+        })
+        .collect();
+
+    let snapshot = snapshot.map(Snapshot::open).transpose()?;
+    let backend = SnapshotBackend::new(MemoryBackend::new(&vicinity, state), snapshot);
     let metadata = StackSubstateMetadata::new(u64::MAX, &config);
     let state = MemoryStackState::new(metadata, &backend);
     let precompiles = BTreeMap::new();
     let mut executor = StackExecutor::new_with_precompiles(state, &config, &precompiles);
 
-    let (reason, res) = executor.transact_call(
-        caller_address,
-        dest_address,
-        U256::from(value),
-        Vec::from(calldata),
-        u64::MAX,
-        Vec::new(),
-    );
+    let mut listener = StepListener {
+        target: dest_address,
+        steps: vec![],
+    };
+    let (reason, res) = evm_runtime::tracing::using(&mut listener, || {
+        executor.transact_call(
+            caller_address,
+            dest_address,
+            U256::from(value),
+            Vec::from(calldata),
+            env.gas_limit,
+            Vec::new(),
+        )
+    });
+    let trace = listener.steps;
+    let exit_pc = trace.iter().rev().find(|step| step.target).map(|step| step.pc);
 
-    access_accounts(&mut executor.state_mut().metadata_mut(), &accounts_input);
+    access_accounts(executor.state_mut().metadata_mut(), &accounts_input);
 
     let mut accounts_output = vec![];
     let state = executor.state();
@@ -134,7 +209,7 @@ pub(crate) fn execute(
 
         // set account data for accounts who are accessed.
         for addr in &accessed.accessed_addresses {
-            if !acct_tree.contains_key(&addr) {
+            if !acct_tree.contains_key(addr) {
                 let addr = *addr;
                 let balance = state.basic(addr).balance;
                 let nonce = state.basic(addr).nonce;
@@ -151,6 +226,23 @@ pub(crate) fn execute(
         }
     }
 
+    let used_gas = executor.used_gas();
+    let (applies, logs) = executor.into_state().deconstruct();
+    let logs: Vec<Log> = logs.into_iter().collect();
+
+    if let Some(err) = backend.take_error() {
+        return Err(err);
+    }
+    // snapshot accounts the test read become part of its pre-state.
+    let touched = backend.touched();
+    for acct in &touched {
+        pre_state.insert(acct.address, acct.clone());
+    }
+    accounts_input.extend(touched);
+
+    let mut post_state = pre_state.clone();
+    apply_changes(&mut post_state, applies, !config.empty_considered_exists);
+
     Ok(ExecutionResult {
         code: Vec::from(code),
         value,
@@ -159,9 +251,65 @@ pub(crate) fn execute(
         accounts_input,
         accounts_output,
         result: reason,
+        trace,
+        exit_pc,
+        pre_state: pre_state.into_values().collect(),
+        post_state: post_state.into_values().collect(),
+        logs,
+        used_gas,
     })
 }
 
+// applies the changes of a call to `state`. with `delete_empty` (from spurious dragon on),
+// touched accounts left without balance, nonce and code are removed.
+fn apply_changes<A, I>(state: &mut BTreeMap<H160, NormalizedAccount>, applies: A, delete_empty: bool)
+where
+    A: IntoIterator<Item = Apply<I>>,
+    I: IntoIterator<Item = (H256, H256)>,
+{
+    for apply in applies {
+        match apply {
+            Apply::Modify {
+                address,
+                basic,
+                code,
+                storage,
+                reset_storage,
+            } => {
+                let acct = state.entry(address).or_insert_with(|| NormalizedAccount {
+                    address,
+                    balance: U256::zero(),
+                    nonce: U256::zero(),
+                    code: vec![],
+                    storage: BTreeMap::new(),
+                });
+                acct.balance = basic.balance;
+                acct.nonce = basic.nonce;
+                if let Some(code) = code {
+                    acct.code = code;
+                }
+                if reset_storage {
+                    acct.storage.clear();
+                }
+                for (key, value) in storage {
+                    if value.is_zero() {
+                        acct.storage.remove(&key);
+                    } else {
+                        acct.storage.insert(key, value);
+                    }
+                }
+                let empty = acct.balance.is_zero() && acct.nonce.is_zero() && acct.code.is_empty();
+                if delete_empty && empty {
+                    state.remove(&address);
+                }
+            }
+            Apply::Delete { address } => {
+                state.remove(&address);
+            }
+        }
+    }
+}
+
 fn access_accounts(metadata: &mut StackSubstateMetadata, accounts: &[NormalizedAccount]) {
     for acct in accounts {
         metadata.access_address(acct.address);
@@ -169,3 +317,62 @@ fn access_accounts(metadata: &mut StackSubstateMetadata, accounts: &[NormalizedA
         metadata.access_storages(addr_keys);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use evm::backend::Basic;
+
+    fn modify(
+        address: H160,
+        balance: u64,
+        storage: Vec<(H256, H256)>,
+        reset_storage: bool,
+    ) -> Apply<Vec<(H256, H256)>> {
+        Apply::Modify {
+            address,
+            basic: Basic {
+                balance: U256::from(balance),
+                nonce: U256::zero(),
+            },
+            code: None,
+            storage,
+            reset_storage,
+        }
+    }
+
+    fn slot(n: u64) -> H256 {
+        H256::from_low_u64_be(n)
+    }
+
+    #[test]
+    fn apply_changes_updates_storage() {
+        let address = H160::from_low_u64_be(1);
+        let mut state = BTreeMap::new();
+        let storage = vec![(slot(1), slot(10)), (slot(2), slot(20))];
+        apply_changes(&mut state, vec![modify(address, 1, storage, false)], false);
+        assert_eq!(state[&address].balance, U256::one());
+        assert_eq!(state[&address].storage.len(), 2);
+
+        // zero values delete slots, a reset drops the slots not written again.
+        apply_changes(&mut state, vec![modify(address, 1, vec![(slot(1), H256::zero())], false)], false);
+        assert_eq!(state[&address].storage, BTreeMap::from([(slot(2), slot(20))]));
+        apply_changes(&mut state, vec![modify(address, 1, vec![(slot(3), slot(30))], true)], false);
+        assert_eq!(state[&address].storage, BTreeMap::from([(slot(3), slot(30))]));
+    }
+
+    #[test]
+    fn apply_changes_deletes_accounts() {
+        let address = H160::from_low_u64_be(1);
+        let mut state = BTreeMap::new();
+        apply_changes(&mut state, vec![modify(address, 0, vec![], false)], false);
+        assert!(state.contains_key(&address));
+        apply_changes(&mut state, vec![modify(address, 0, vec![], false)], true);
+        assert!(!state.contains_key(&address));
+
+        apply_changes(&mut state, vec![modify(address, 1, vec![], false)], true);
+        assert!(state.contains_key(&address));
+        apply_changes(&mut state, vec![Apply::<Vec<(H256, H256)>>::Delete { address }], true);
+        assert!(state.is_empty());
+    }
+}
@@ -1 +1,3 @@
-pub mod executor;
\ No newline at end of file
+#[allow(clippy::module_inception)]
+pub mod executor;
+pub mod snapshot;
@@ -0,0 +1,269 @@
+use anyhow::{anyhow, bail, Context};
+use evm::backend::{Backend, Basic, MemoryBackend};
+use primitive_types::{H160, H256, U256};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::core::{parse_address, parse_u256, parse_word, NormalizedAccount};
+
+// pre-state read from disk: a geth style alloc json file
+// `{ "<address>": { "balance", "nonce", "code", "storage" } }`, or a directory holding one
+// `<address>.json` alloc entry per account. nothing is read before the first access.
+pub(crate) struct Snapshot {
+    path: PathBuf,
+    // entries of the alloc file by address, parsed on first access.
+    alloc: RefCell<Option<BTreeMap<H160, Value>>>,
+    // accounts converted so far, `None` when the snapshot does not have one.
+    accounts: RefCell<BTreeMap<H160, Option<Rc<NormalizedAccount>>>>,
+}
+
+impl Snapshot {
+    pub fn open(path: &str) -> anyhow::Result<Snapshot> {
+        let path = PathBuf::from(path);
+        if !path.exists() {
+            bail!("snapshot {} does not exist", path.display());
+        }
+        Ok(Snapshot {
+            path,
+            alloc: RefCell::new(None),
+            accounts: RefCell::new(BTreeMap::new()),
+        })
+    }
+
+    fn account(&self, address: H160) -> anyhow::Result<Option<Rc<NormalizedAccount>>> {
+        if let Some(account) = self.accounts.borrow().get(&address) {
+            return Ok(account.clone());
+        }
+        let entry = if self.path.is_dir() {
+            self.read_entry(address)?
+        } else {
+            self.alloc_entry(address)?
+        };
+        let account = entry
+            .map(|entry| to_account(address, &entry))
+            .transpose()
+            .with_context(|| format!("{}: invalid account {:?}", self.path.display(), address))?
+            .map(Rc::new);
+        self.accounts.borrow_mut().insert(address, account.clone());
+        Ok(account)
+    }
+
+    fn alloc_entry(&self, address: H160) -> anyhow::Result<Option<Value>> {
+        if self.alloc.borrow().is_none() {
+            let data = fs::read_to_string(&self.path)?;
+            let alloc: serde_json::Map<String, Value> = serde_json::from_str(&data)
+                .with_context(|| format!("invalid snapshot {}", self.path.display()))?;
+            let mut entries = BTreeMap::new();
+            for (key, entry) in alloc {
+                entries.insert(parse_address(&key)?, entry);
+            }
+            *self.alloc.borrow_mut() = Some(entries);
+        }
+        let alloc = self.alloc.borrow();
+        Ok(alloc
+            .as_ref()
+            .and_then(|alloc| alloc.get(&address).cloned()))
+    }
+
+    fn read_entry(&self, address: H160) -> anyhow::Result<Option<Value>> {
+        let name = hex::encode(address);
+        for filename in [format!("{}.json", name), format!("0x{}.json", name)] {
+            let path = self.path.join(filename);
+            if path.is_file() {
+                let data = fs::read_to_string(&path)?;
+                let entry = serde_json::from_str(&data)
+                    .with_context(|| format!("invalid snapshot entry {}", path.display()))?;
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+}
+
+// an alloc entry `{ "balance", "nonce", "code", "storage" }`, all optional.
+pub(crate) fn to_account(address: H160, entry: &Value) -> anyhow::Result<NormalizedAccount> {
+    let mut storage = BTreeMap::new();
+    if let Some(slots) = entry["storage"].as_object() {
+        for (key, value) in slots {
+            let value = value
+                .as_str()
+                .ok_or(anyhow!("storage value of {} is not a string", key))?;
+            storage.insert(parse_word(key)?, parse_word(value)?);
+        }
+    }
+    let code = entry["code"]
+        .as_str()
+        .unwrap_or_default()
+        .trim_start_matches("0x");
+    Ok(NormalizedAccount {
+        address,
+        balance: to_quantity(&entry["balance"])?,
+        nonce: to_quantity(&entry["nonce"])?,
+        code: hex::decode(code)?,
+        storage,
+    })
+}
+
+// a hex (0x) or decimal string, or a number. json numbers above u64 are read as floats and
+// lose precision, so they are rejected. geth writes them as strings.
+fn to_quantity(value: &Value) -> anyhow::Result<U256> {
+    let quantity = match value {
+        Value::Null => U256::zero(),
+        Value::Number(number) => match number.as_u64() {
+            Some(number) => U256::from(number),
+            None => bail!("invalid quantity {}, write it as a hex or decimal string", number),
+        },
+        Value::String(s) => parse_u256(s)?,
+        _ => bail!("invalid quantity {}", value),
+    };
+    Ok(quantity)
+}
+
+// the test accounts in memory, falling back to the snapshot for any other account. accounts read
+// from the snapshot are kept with the storage slots that were read, so a test can replay them.
+pub(crate) struct SnapshotBackend<'vicinity> {
+    memory: MemoryBackend<'vicinity>,
+    snapshot: Option<Snapshot>,
+    touched: RefCell<BTreeMap<H160, NormalizedAccount>>,
+    // the backend interface cannot fail, the first read error is kept here instead.
+    error: RefCell<Option<anyhow::Error>>,
+}
+
+impl<'vicinity> SnapshotBackend<'vicinity> {
+    pub fn new(memory: MemoryBackend<'vicinity>, snapshot: Option<Snapshot>) -> Self {
+        SnapshotBackend {
+            memory,
+            snapshot,
+            touched: RefCell::new(BTreeMap::new()),
+            error: RefCell::new(None),
+        }
+    }
+
+    // snapshot accounts read so far.
+    pub fn touched(&self) -> Vec<NormalizedAccount> {
+        self.touched.borrow().values().cloned().collect()
+    }
+
+    pub fn take_error(&self) -> Option<anyhow::Error> {
+        self.error.borrow_mut().take()
+    }
+
+    fn snapshot_account(&self, address: H160) -> Option<Rc<NormalizedAccount>> {
+        if self.memory.state().contains_key(&address) {
+            return None;
+        }
+        let account = match self.snapshot.as_ref()?.account(address) {
+            Ok(account) => account?,
+            Err(err) => {
+                self.error.borrow_mut().get_or_insert(err);
+                return None;
+            }
+        };
+        self.touched
+            .borrow_mut()
+            .entry(address)
+            .or_insert_with(|| NormalizedAccount {
+                address,
+                balance: account.balance,
+                nonce: account.nonce,
+                code: account.code.clone(),
+                storage: BTreeMap::new(),
+            });
+        Some(account)
+    }
+}
+
+impl<'vicinity> Backend for SnapshotBackend<'vicinity> {
+    fn gas_price(&self) -> U256 {
+        self.memory.gas_price()
+    }
+    fn origin(&self) -> H160 {
+        self.memory.origin()
+    }
+    fn block_hash(&self, number: U256) -> H256 {
+        self.memory.block_hash(number)
+    }
+    fn block_number(&self) -> U256 {
+        self.memory.block_number()
+    }
+    fn block_coinbase(&self) -> H160 {
+        self.memory.block_coinbase()
+    }
+    fn block_timestamp(&self) -> U256 {
+        self.memory.block_timestamp()
+    }
+    fn block_difficulty(&self) -> U256 {
+        self.memory.block_difficulty()
+    }
+    fn block_gas_limit(&self) -> U256 {
+        self.memory.block_gas_limit()
+    }
+    fn block_base_fee_per_gas(&self) -> U256 {
+        self.memory.block_base_fee_per_gas()
+    }
+    fn chain_id(&self) -> U256 {
+        self.memory.chain_id()
+    }
+
+    fn exists(&self, address: H160) -> bool {
+        self.memory.exists(address) || self.snapshot_account(address).is_some()
+    }
+
+    fn basic(&self, address: H160) -> Basic {
+        match self.snapshot_account(address) {
+            Some(account) => Basic {
+                balance: account.balance,
+                nonce: account.nonce,
+            },
+            None => self.memory.basic(address),
+        }
+    }
+
+    fn code(&self, address: H160) -> Vec<u8> {
+        match self.snapshot_account(address) {
+            Some(account) => account.code.clone(),
+            None => self.memory.code(address),
+        }
+    }
+
+    fn storage(&self, address: H160, index: H256) -> H256 {
+        match self.snapshot_account(address) {
+            Some(account) => {
+                let value = account.storage.get(&index).cloned().unwrap_or_default();
+                if let Some(touched) = self.touched.borrow_mut().get_mut(&address) {
+                    touched.storage.insert(index, value);
+                }
+                value
+            }
+            None => self.memory.storage(address, index),
+        }
+    }
+
+    fn original_storage(&self, address: H160, index: H256) -> Option<H256> {
+        Some(self.storage(address, index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn to_account_reads_quantities() {
+        let address = H160::from_low_u64_be(1);
+        let entry = json!({ "balance": "100000000000000000000", "nonce": 1, "code": "0x", "storage": { "0x1": "0x2" } });
+        let account = to_account(address, &entry).unwrap();
+        assert_eq!(account.balance, U256::exp10(20));
+        assert_eq!(account.nonce, U256::one());
+        assert_eq!(account.storage[&H256::from_low_u64_be(1)], H256::from_low_u64_be(2));
+        assert_eq!(to_account(address, &json!({ "balance": "0x" })).unwrap().balance, U256::zero());
+
+        let entry: Value = serde_json::from_str(r#"{ "balance": 100000000000000000000 }"#).unwrap();
+        assert!(to_account(address, &entry).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use handlebars::Handlebars;
+use primitive_types::{H160, U256};
+use serde::Serialize;
+use sha3::{Digest, Keccak256};
+use std::fs;
+
+use crate::core::{exit_name, NormalizedAccount, TestCase};
+use crate::mover::mover::move_identifier;
+
+const FORGE_TEMPLATE: &str = include_str!("../../templates/forge.hbs");
+
+// variables of the forge template. addresses are eip-55 checksummed literals, other hex values
+// are without 0x and numbers are decimal strings.
+#[derive(Serialize)]
+struct ForgeTestGroup {
+    name: String,
+    tests: Vec<ForgeTest>,
+}
+
+#[derive(Serialize)]
+struct ForgeTest {
+    name: String,
+    source: String,
+    names: Vec<ForgeName>,
+    caller: String,
+    origin: String,
+    to: String,
+    calldata: String,
+    value: String,
+    output: String,
+    success: bool,
+    exit: String,
+    env: ForgeEnv,
+    accounts_input: Vec<ForgeAccount>,
+    accounts_output: Vec<ForgeAccount>,
+}
+
+#[derive(Serialize)]
+struct ForgeName {
+    name: String,
+    address: String,
+}
+
+#[derive(Serialize)]
+struct ForgeEnv {
+    number: String,
+    timestamp: String,
+    coinbase: String,
+    // empty when zero. set with `vm.prevrandao` when `vm.difficulty` is not available, after
+    // the merge.
+    difficulty: String,
+    base_fee: String,
+    chain_id: String,
+    gas_price: String,
+}
+
+#[derive(Serialize)]
+struct ForgeAccount {
+    address: String,
+    balance: String,
+    nonce: String,
+    // `vm.setNonce` cannot set a nonce of 0.
+    set_nonce: bool,
+    // a call from a test does not increment the nonce of its caller like a transaction does.
+    check_nonce: bool,
+    code: String,
+    storage: Vec<ForgeSlot>,
+}
+
+#[derive(Serialize)]
+struct ForgeSlot {
+    key: String,
+    value: String,
+}
+
+// eip-55 mixed case address, e.g. `0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed`.
+fn checksum(address: &H160) -> String {
+    let lower = hex::encode(address);
+    let hash = hex::encode(Keccak256::digest(lower.as_bytes()));
+    let mixed: String = lower
+        .chars()
+        .zip(hash.chars())
+        .map(|(c, h)| match h.to_digit(16) {
+            Some(n) if n >= 8 => c.to_ascii_uppercase(),
+            _ => c,
+        })
+        .collect();
+    format!("0x{}", mixed)
+}
+
+fn to_forge_account(acct: &NormalizedAccount, caller: &H160) -> ForgeAccount {
+    ForgeAccount {
+        address: checksum(&acct.address),
+        balance: acct.balance.to_string(),
+        nonce: acct.nonce.to_string(),
+        set_nonce: !acct.nonce.is_zero(),
+        check_nonce: acct.address != *caller,
+        code: hex::encode(&acct.code),
+        storage: acct
+            .storage
+            .iter()
+            .map(|(key, value)| ForgeSlot {
+                key: hex::encode(key),
+                value: hex::encode(value),
+            })
+            .collect(),
+    }
+}
+
+fn to_forge_test(tc: &TestCase) -> ForgeTest {
+    let env = &tc.env;
+    let difficulty = if env.block_difficulty.is_zero() {
+        "".to_owned()
+    } else {
+        env.block_difficulty.to_string()
+    };
+    ForgeTest {
+        name: tc.funcname.to_owned(),
+        source: tc.source.to_owned(),
+        names: tc
+            .names
+            .iter()
+            .map(|(name, address)| ForgeName {
+                name: name.to_owned(),
+                address: checksum(address),
+            })
+            .collect(),
+        caller: checksum(&env.caller),
+        origin: checksum(&env.origin),
+        to: checksum(&env.address),
+        calldata: hex::encode(&tc.calldata),
+        value: U256::from(tc.value).to_string(),
+        output: hex::encode(&tc.output),
+        success: matches!(tc.result, evm::ExitReason::Succeed(_)),
+        exit: exit_name(&tc.result),
+        env: ForgeEnv {
+            number: env.block_number.to_string(),
+            timestamp: env.block_timestamp.to_string(),
+            coinbase: checksum(&env.block_coinbase),
+            difficulty,
+            base_fee: env.block_base_fee_per_gas.to_string(),
+            chain_id: env.chain_id.to_string(),
+            gas_price: env.gas_price.to_string(),
+        },
+        accounts_input: tc
+            .pre_state
+            .iter()
+            .map(|acct| to_forge_account(acct, &env.caller))
+            .collect(),
+        accounts_output: tc
+            .accounts_output
+            .iter()
+            .map(|acct| to_forge_account(acct, &env.caller))
+            .collect(),
+    }
+}
+
+// writes the tests of a group as `forge test` contracts, one per test, into one file.
+pub(crate) fn write_forge_testgroup(
+    test_group_name: &str,
+    filepath: &str,
+    testcases: &[TestCase],
+) -> anyhow::Result<()> {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(handlebars::no_escape);
+    handlebars.set_strict_mode(true);
+    handlebars.register_template_string("group", FORGE_TEMPLATE)?;
+
+    let group = ForgeTestGroup {
+        name: move_identifier(test_group_name),
+        tests: testcases.iter().map(to_forge_test).collect(),
+    };
+    fs::write(filepath, handlebars.render("group", &group)?)?;
+    Ok(())
+}
@@ -0,0 +1,229 @@
+use anyhow::{anyhow, bail};
+use primitive_types::{H160, H256, U256};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use crate::core::{AccountDeseriarizable, Env, NormalizedAccount, TestCase};
+use crate::executor::executor::{execute, fork_config, ExecutionResult};
+
+pub(crate) mod forge;
+pub(crate) mod rust;
+pub(crate) mod statetest;
+pub(crate) mod t8n;
+pub(crate) mod trie;
+
+// the sender of ethereum/tests state tests and its secret key.
+pub(crate) const SENDER: &str = "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b";
+pub(crate) const SECRET_KEY: &str =
+    "0x45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8";
+
+// the env of a test for external runners. the default caller becomes `SENDER`, which is also the
+// origin, and the block gas limit covers the transaction. fails for tests the runners cannot
+// reproduce.
+pub(crate) fn external_env(env: &Env) -> anyhow::Result<Env> {
+    let default = Env::default();
+    if env.caller != default.caller {
+        bail!("caller {:?} has no known secret key", env.caller);
+    }
+    if env.origin != default.origin && env.origin != env.caller {
+        bail!("origin {:?} is not the caller", env.origin);
+    }
+    if !env.gas_price.is_zero() || !env.block_base_fee_per_gas.is_zero() {
+        bail!("gas fees are not charged by the executor, gas_price and base_fee must be 0");
+    }
+    if env.chain_id != U256::one() {
+        bail!("chain id {} is not 1", env.chain_id);
+    }
+    let mut env = env.clone();
+    env.caller = H160::from_str(SENDER)?;
+    env.origin = env.caller;
+    env.block_gas_limit = env.block_gas_limit.max(U256::from(env.gas_limit));
+    Ok(env)
+}
+
+// runs a test again in its `external_env`, from the accounts it was generated with.
+pub(crate) fn execute_external(tc: &TestCase) -> anyhow::Result<(Env, ExecutionResult)> {
+    let env = external_env(&tc.env)?;
+    let accounts = tc
+        .accounts_input
+        .iter()
+        .map(to_account)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let result = execute(tc.value, &tc.code, &tc.calldata, 0, &accounts, &env, None)?;
+    Ok((env, result))
+}
+
+fn to_account(acct: &NormalizedAccount) -> anyhow::Result<AccountDeseriarizable> {
+    let storage: BTreeMap<String, String> = acct
+        .storage
+        .iter()
+        .map(|(key, value)| (hex::encode(key), hex::encode(value)))
+        .collect();
+    Ok(AccountDeseriarizable {
+        address: hex::encode(acct.address),
+        balance: acct
+            .balance
+            .try_into()
+            .map_err(|_| anyhow!("balance of {:?} exceeds 128 bits", acct.address))?,
+        nonce: acct
+            .nonce
+            .try_into()
+            .map_err(|_| anyhow!("nonce of {:?} exceeds 128 bits", acct.address))?,
+        code: hex::encode(&acct.code),
+        storage,
+    })
+}
+
+pub(crate) fn sender_nonce(env: &Env, pre_state: &[NormalizedAccount]) -> U256 {
+    pre_state
+        .iter()
+        .find(|acct| acct.address == env.caller)
+        .map(|acct| acct.nonce)
+        .unwrap_or_default()
+}
+
+// `0x` prefixed hex quantity, e.g. `0x1`.
+pub(crate) fn quantity(value: U256) -> String {
+    format!("{:#x}", value)
+}
+
+// the fork name of state tests and t8n.
+pub(crate) fn fork_name(fork: &str) -> anyhow::Result<&'static str> {
+    let name = match fork.to_ascii_lowercase().as_str() {
+        "frontier" => "Frontier",
+        "istanbul" => "Istanbul",
+        "berlin" => "Berlin",
+        "london" => "London",
+        _ => bail!("unsupported fork {:?}", fork),
+    };
+    Ok(name)
+}
+
+fn word(word: &H256) -> String {
+    quantity(U256::from_big_endian(word.as_bytes()))
+}
+
+pub(crate) fn alloc(accounts: &[NormalizedAccount]) -> Value {
+    let mut alloc = Map::new();
+    for acct in accounts {
+        let storage: Map<String, Value> = acct
+            .storage
+            .iter()
+            .map(|(key, value)| (word(key), json!(word(value))))
+            .collect();
+        let account = json!({
+            "balance": quantity(acct.balance),
+            "code": format!("0x{}", hex::encode(&acct.code)),
+            "nonce": quantity(acct.nonce),
+            "storage": storage,
+        });
+        alloc.insert(format!("{:?}", acct.address), account);
+    }
+    Value::Object(alloc)
+}
+
+// the post-state as the runners compute it. they pay the (zero) fee to the coinbase, which creates
+// it before spurious dragon and removes it when empty from then on.
+pub(crate) fn runner_post_state(
+    env: &Env,
+    mut post: Vec<NormalizedAccount>,
+) -> anyhow::Result<Vec<NormalizedAccount>> {
+    let coinbase = post.iter().position(|acct| acct.address == env.block_coinbase);
+    if fork_config(&env.fork)?.empty_considered_exists {
+        if coinbase.is_none() {
+            post.push(NormalizedAccount {
+                address: env.block_coinbase,
+                balance: U256::zero(),
+                nonce: U256::zero(),
+                code: vec![],
+                storage: Default::default(),
+            });
+            post.sort_by_key(|acct| acct.address);
+        }
+    } else if let Some(i) = coinbase {
+        let acct = &post[i];
+        if acct.balance.is_zero() && acct.nonce.is_zero() && acct.code.is_empty() {
+            post.remove(i);
+        }
+    }
+    Ok(post)
+}
+
+// charges the fee the executor leaves out: the sender pays `gas_price` for every used gas and
+// the coinbase gets it, less the burnt base fee from london on.
+pub(crate) fn charge_fees(
+    env: &Env,
+    mut post: Vec<NormalizedAccount>,
+    used_gas: u64,
+) -> anyhow::Result<Vec<NormalizedAccount>> {
+    let used_gas = U256::from(used_gas);
+    let priority_fee = if env.fork.eq_ignore_ascii_case("london") {
+        env.gas_price
+            .checked_sub(env.block_base_fee_per_gas)
+            .ok_or(anyhow!("gas price {} is below the base fee {}", env.gas_price, env.block_base_fee_per_gas))?
+    } else {
+        env.gas_price
+    };
+
+    let sender = post
+        .iter_mut()
+        .find(|acct| acct.address == env.caller)
+        .ok_or(anyhow!("sender {:?} is not in the post-state", env.caller))?;
+    sender.balance = sender
+        .balance
+        .checked_sub(used_gas * env.gas_price)
+        .ok_or(anyhow!("sender {:?} cannot pay the fee", env.caller))?;
+
+    match post.iter_mut().find(|acct| acct.address == env.block_coinbase) {
+        Some(coinbase) => coinbase.balance += used_gas * priority_fee,
+        None => {
+            post.push(NormalizedAccount {
+                address: env.block_coinbase,
+                balance: used_gas * priority_fee,
+                nonce: U256::zero(),
+                code: vec![],
+                storage: Default::default(),
+            });
+            post.sort_by_key(|acct| acct.address);
+        }
+    }
+    Ok(post)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(address: u64, balance: u64) -> NormalizedAccount {
+        NormalizedAccount {
+            address: H160::from_low_u64_be(address),
+            balance: U256::from(balance),
+            nonce: U256::zero(),
+            code: vec![],
+            storage: Default::default(),
+        }
+    }
+
+    #[test]
+    fn charge_fees_burns_the_base_fee_from_london_on() {
+        let env = Env {
+            caller: H160::from_low_u64_be(1),
+            block_coinbase: H160::from_low_u64_be(2),
+            gas_price: U256::from(12),
+            block_base_fee_per_gas: U256::from(10),
+            fork: "london".to_owned(),
+            ..Env::default()
+        };
+        let post = charge_fees(&env, vec![account(1, 1_000_000)], 21_000).unwrap();
+        assert_eq!(post[0].balance, U256::from(1_000_000 - 21_000 * 12));
+        assert_eq!(post[1].address, env.block_coinbase);
+        assert_eq!(post[1].balance, U256::from(21_000 * 2));
+
+        let env = Env { fork: "berlin".to_owned(), ..env };
+        let post = charge_fees(&env, vec![account(1, 1_000_000), account(2, 5)], 21_000).unwrap();
+        assert_eq!(post[1].balance, U256::from(5 + 21_000 * 12));
+
+        assert!(charge_fees(&env, vec![account(1, 100)], 21_000).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+use anyhow::bail;
+use handlebars::Handlebars;
+use primitive_types::U256;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use super::{fork_name, quantity};
+use crate::core::{exit_name, NormalizedAccount, TestCase};
+use crate::mover::mover::unique_identifier;
+
+const RUST_TEMPLATE: &str = include_str!("../../templates/rust.hbs");
+const HARNESS: &str = include_str!("../../templates/harness.rs");
+
+// variables of the rust template. values are hex strings as read by the harness.
+#[derive(Serialize)]
+struct RustTestGroup {
+    name: String,
+    tests: Vec<RustTest>,
+}
+
+#[derive(Serialize)]
+struct RustTest {
+    name: String,
+    // the name of the `Test` constant, the upper case test name made unique within the group.
+    constant: String,
+    source: String,
+    // `source` as a rust string literal.
+    source_literal: String,
+    names: Vec<RustName>,
+    // as named by the harness, e.g. `London`.
+    fork: String,
+    caller: String,
+    origin: String,
+    address: String,
+    value: String,
+    calldata: String,
+    gas_limit: u64,
+    gas_price: String,
+    block_number: String,
+    block_coinbase: String,
+    block_timestamp: String,
+    block_difficulty: String,
+    block_gas_limit: String,
+    block_base_fee_per_gas: String,
+    chain_id: String,
+    pre: Vec<RustFixture>,
+    success: bool,
+    exit: String,
+    output: String,
+    post: Vec<RustFixture>,
+}
+
+#[derive(Serialize)]
+struct RustName {
+    name: String,
+    address: String,
+}
+
+#[derive(Serialize)]
+struct RustFixture {
+    address: String,
+    balance: String,
+    nonce: u64,
+    code: String,
+    storage: Vec<RustSlot>,
+}
+
+#[derive(Serialize)]
+struct RustSlot {
+    key: String,
+    value: String,
+}
+
+// test names are move identifiers, which may be rust keywords.
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use",
+    "where", "while", "abstract", "become", "box", "do", "final", "macro", "override", "priv",
+    "try", "typeof", "unsized", "virtual", "yield",
+];
+
+fn rust_identifier(name: &str) -> String {
+    if RUST_KEYWORDS.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_owned()
+    }
+}
+
+// upper case test names. names differing only in case get `_2`, `_3`, .. appended.
+fn constants(funcnames: &[&str]) -> Vec<String> {
+    let mut constants: Vec<String> = vec![];
+    for funcname in funcnames {
+        let taken: Vec<&str> = constants.iter().map(|constant| constant.as_str()).collect();
+        let constant = unique_identifier(&funcname.to_uppercase(), &taken);
+        constants.push(constant);
+    }
+    constants
+}
+
+fn to_fixture(acct: &NormalizedAccount) -> anyhow::Result<RustFixture> {
+    if acct.nonce > U256::from(u64::MAX) {
+        bail!("nonce of {:?} exceeds 64 bits", acct.address);
+    }
+    Ok(RustFixture {
+        address: format!("{:?}", acct.address),
+        balance: quantity(acct.balance),
+        nonce: acct.nonce.as_u64(),
+        code: format!("0x{}", hex::encode(&acct.code)),
+        storage: acct
+            .storage
+            .iter()
+            .map(|(key, value)| RustSlot {
+                key: format!("{:?}", key),
+                value: format!("{:?}", value),
+            })
+            .collect(),
+    })
+}
+
+fn to_rust_test(tc: &TestCase, constant: String) -> anyhow::Result<RustTest> {
+    let env = &tc.env;
+    Ok(RustTest {
+        name: rust_identifier(&tc.funcname),
+        constant,
+        source: tc.source.to_owned(),
+        source_literal: format!("{:?}", tc.source),
+        names: tc
+            .names
+            .iter()
+            .map(|(name, address)| RustName {
+                name: name.to_owned(),
+                address: format!("{:?}", address),
+            })
+            .collect(),
+        fork: fork_name(&env.fork)?.to_owned(),
+        caller: format!("{:?}", env.caller),
+        origin: format!("{:?}", env.origin),
+        address: format!("{:?}", env.address),
+        value: quantity(U256::from(tc.value)),
+        calldata: format!("0x{}", hex::encode(&tc.calldata)),
+        gas_limit: env.gas_limit,
+        gas_price: quantity(env.gas_price),
+        block_number: quantity(env.block_number),
+        block_coinbase: format!("{:?}", env.block_coinbase),
+        block_timestamp: quantity(env.block_timestamp),
+        block_difficulty: quantity(env.block_difficulty),
+        block_gas_limit: quantity(env.block_gas_limit),
+        block_base_fee_per_gas: quantity(env.block_base_fee_per_gas),
+        chain_id: quantity(env.chain_id),
+        pre: tc.pre_state.iter().map(to_fixture).collect::<anyhow::Result<_>>()?,
+        success: matches!(tc.result, evm::ExitReason::Succeed(_)),
+        exit: exit_name(&tc.result),
+        output: format!("0x{}", hex::encode(&tc.output)),
+        post: tc.accounts_output.iter().map(to_fixture).collect::<anyhow::Result<_>>()?,
+    })
+}
+
+// writes `<dir>/harness.rs`, the trait the evm under test implements and the runner of the
+// tests.
+pub(crate) fn write_rust_harness(dir: &str) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(Path::new(dir).join("harness.rs"), HARNESS)?;
+    Ok(())
+}
+
+// writes the tests of a group as a rust module of `Test` constants and a `#[test]` per test.
+pub(crate) fn write_rust_testgroup(
+    test_group_name: &str,
+    filepath: &str,
+    testcases: &[TestCase],
+) -> anyhow::Result<()> {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(handlebars::no_escape);
+    handlebars.set_strict_mode(true);
+    handlebars.register_template_string("group", RUST_TEMPLATE)?;
+
+    let funcnames: Vec<&str> = testcases.iter().map(|tc| tc.funcname.as_str()).collect();
+    let tests = testcases
+        .iter()
+        .zip(constants(&funcnames))
+        .map(|(tc, constant)| to_rust_test(tc, constant))
+        .collect::<anyhow::Result<_>>()?;
+    let group = RustTestGroup {
+        name: test_group_name.to_owned(),
+        tests,
+    };
+    fs::write(filepath, handlebars.render("group", &group)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitive_types::H160;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn constants_are_unique() {
+        assert_eq!(constants(&["add", "Add", "sub", "ADD"]), vec!["ADD", "ADD_2", "SUB", "ADD_3"]);
+    }
+
+    #[test]
+    fn to_fixture_rejects_nonce_above_u64() {
+        let mut acct = NormalizedAccount {
+            address: H160::zero(),
+            balance: U256::zero(),
+            nonce: U256::from(u64::MAX),
+            code: vec![],
+            storage: BTreeMap::new(),
+        };
+        assert_eq!(to_fixture(&acct).unwrap().nonce, u64::MAX);
+        acct.nonce += U256::one();
+        assert!(to_fixture(&acct).is_err());
+    }
+}
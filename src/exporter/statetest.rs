@@ -0,0 +1,73 @@
+use primitive_types::{H256, U256};
+use serde_json::{json, Map, Value};
+use std::fs;
+
+use super::trie::{logs_hash, state_root};
+use super::{
+    alloc, execute_external, fork_name, quantity, runner_post_state, sender_nonce, SECRET_KEY,
+    SENDER,
+};
+use crate::core::TestCase;
+
+// a filled GeneralStateTests test of one transaction from `SENDER` to the test code. `post` holds
+// the state root and logs hash of the test fork, and the full post-state as `postState`.
+pub(crate) fn to_statetest(tc: &TestCase) -> anyhow::Result<Value> {
+    let (env, result) = execute_external(tc)?;
+    let fork = fork_name(&env.fork)?;
+    let post = runner_post_state(&env, result.post_state)?;
+    let nonce = sender_nonce(&env, &result.pre_state);
+    Ok(json!({
+        "_info": {
+            "comment": "generated by evm-tester",
+            "source": tc.source,
+        },
+        "env": {
+            "currentCoinbase": format!("{:?}", env.block_coinbase),
+            "currentDifficulty": quantity(env.block_difficulty),
+            "currentGasLimit": quantity(env.block_gas_limit),
+            "currentNumber": quantity(env.block_number),
+            "currentTimestamp": quantity(env.block_timestamp),
+            "currentBaseFee": quantity(env.block_base_fee_per_gas),
+            "previousHash": format!("{:?}", H256::zero()),
+        },
+        "pre": alloc(&result.pre_state),
+        "transaction": {
+            "data": [format!("0x{}", hex::encode(&tc.calldata))],
+            "gasLimit": [quantity(U256::from(env.gas_limit))],
+            "gasPrice": quantity(env.gas_price),
+            "nonce": quantity(nonce),
+            "secretKey": SECRET_KEY,
+            "sender": SENDER,
+            "to": format!("{:?}", env.address),
+            "value": [quantity(U256::from(tc.value))],
+        },
+        "post": {
+            fork: [{
+                "hash": format!("{:?}", state_root(&post)),
+                "logs": format!("{:?}", logs_hash(&result.logs)),
+                "indexes": { "data": 0, "gas": 0, "value": 0 },
+                "postState": alloc(&post),
+            }],
+        },
+    }))
+}
+
+// writes the tests of a group into one state test file, named by their move identifiers.
+// returns the tests that cannot be exported.
+pub(crate) fn write_statetest_group(
+    filepath: &str,
+    testcases: &[TestCase],
+) -> anyhow::Result<Vec<(String, anyhow::Error)>> {
+    let mut tests = Map::new();
+    let mut skipped = vec![];
+    for tc in testcases {
+        match to_statetest(tc) {
+            Ok(test) => {
+                tests.insert(tc.funcname.to_owned(), test);
+            }
+            Err(err) => skipped.push((tc.source.to_owned(), err)),
+        }
+    }
+    fs::write(filepath, serde_json::to_string_pretty(&Value::Object(tests))?)?;
+    Ok(skipped)
+}
@@ -0,0 +1,179 @@
+use primitive_types::{H160, H256, U256};
+use rlp::RlpStream;
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+use super::trie::{keccak, logs_hash, state_root};
+use super::{
+    alloc, execute_external, fork_name, quantity, runner_post_state, sender_nonce, SECRET_KEY,
+};
+use crate::core::TestCase;
+use crate::executor::executor::fork_config;
+
+// a legacy transaction, signed for `chain_id` (eip-155) when given.
+pub(crate) struct Transaction {
+    pub nonce: U256,
+    pub gas_price: U256,
+    pub gas: U256,
+    pub to: H160,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub chain_id: Option<U256>,
+}
+
+impl Transaction {
+    fn append_fields(&self, stream: &mut RlpStream) {
+        stream.append(&self.nonce);
+        stream.append(&self.gas_price);
+        stream.append(&self.gas);
+        stream.append(&self.to);
+        stream.append(&self.value);
+        stream.append(&self.data);
+    }
+
+    // (v, r, s) with the key `SECRET_KEY`.
+    pub fn sign(&self) -> anyhow::Result<(U256, H256, H256)> {
+        self.sign_with(SECRET_KEY)
+    }
+
+    fn sign_with(&self, secret_key: &str) -> anyhow::Result<(U256, H256, H256)> {
+        let mut stream = RlpStream::new();
+        match self.chain_id {
+            Some(chain_id) => {
+                stream.begin_list(9);
+                self.append_fields(&mut stream);
+                stream.append(&chain_id);
+                stream.append(&0u8);
+                stream.append(&0u8);
+            }
+            None => {
+                stream.begin_list(6);
+                self.append_fields(&mut stream);
+            }
+        }
+        let hash = keccak(&stream.out());
+
+        let key: [u8; 32] = hex::decode(secret_key.trim_start_matches("0x"))?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("invalid secret key"))?;
+        let secret_key = libsecp256k1::SecretKey::parse(&key)?;
+        let message = libsecp256k1::Message::parse(hash.as_fixed_bytes());
+        let (signature, recovery_id) = libsecp256k1::sign(&message, &secret_key);
+        let v = match self.chain_id {
+            Some(chain_id) => chain_id * 2 + 35 + recovery_id.serialize(),
+            None => U256::from(27 + recovery_id.serialize()),
+        };
+        Ok((
+            v,
+            H256::from(signature.r.b32()),
+            H256::from(signature.s.b32()),
+        ))
+    }
+}
+
+// writes `alloc.json`, `env.json` and `txs.json` of a test for `evm t8n` into `dir`, with `fork`
+// (the `--state.fork`) and `expected.json`, the post-state root, logs hash and accessed accounts
+// to compare the t8n output with, see `import-t8n`.
+pub(crate) fn write_t8n(dir: &Path, tc: &TestCase) -> anyhow::Result<()> {
+    let (env, result) = execute_external(tc)?;
+    let fork = fork_name(&env.fork)?;
+
+    // eip-155 replay protection came with spurious dragon.
+    let chain_id = if fork_config(&env.fork)?.empty_considered_exists {
+        None
+    } else {
+        Some(env.chain_id)
+    };
+    let tx = Transaction {
+        nonce: sender_nonce(&env, &result.pre_state),
+        gas_price: env.gas_price,
+        gas: U256::from(env.gas_limit),
+        to: env.address,
+        value: U256::from(tc.value),
+        data: tc.calldata.clone(),
+        chain_id,
+    };
+    let (v, r, s) = tx.sign()?;
+    let txs = json!([{
+        "type": "0x0",
+        "nonce": quantity(tx.nonce),
+        "gasPrice": quantity(tx.gas_price),
+        "gas": quantity(tx.gas),
+        "to": format!("{:?}", tx.to),
+        "value": quantity(tx.value),
+        "input": format!("0x{}", hex::encode(&tx.data)),
+        "v": quantity(v),
+        "r": format!("{:?}", r),
+        "s": format!("{:?}", s),
+    }]);
+    let t8n_env = json!({
+        "currentCoinbase": format!("{:?}", env.block_coinbase),
+        "currentDifficulty": quantity(env.block_difficulty),
+        "currentGasLimit": quantity(env.block_gas_limit),
+        "currentNumber": quantity(env.block_number),
+        "currentTimestamp": quantity(env.block_timestamp),
+        "currentBaseFee": quantity(env.block_base_fee_per_gas),
+    });
+
+    let post = runner_post_state(&env, result.post_state)?;
+    let expected = json!({
+        "source": tc.source,
+        "stateRoot": format!("{:?}", state_root(&post)),
+        "logsHash": format!("{:?}", logs_hash(&result.logs)),
+        "accounts": alloc(&result.accounts_output),
+    });
+
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join("alloc.json"), serde_json::to_string_pretty(&alloc(&result.pre_state))?)?;
+    fs::write(dir.join("env.json"), serde_json::to_string_pretty(&t8n_env)?)?;
+    fs::write(dir.join("txs.json"), serde_json::to_string_pretty(&txs)?)?;
+    fs::write(dir.join("fork"), fork)?;
+    fs::write(dir.join("expected.json"), serde_json::to_string_pretty(&expected)?)?;
+    Ok(())
+}
+
+// writes the t8n inputs of every test to `<dir>/<test>/`. returns the tests that cannot be
+// exported.
+pub(crate) fn write_t8n_group(
+    dir: &str,
+    testcases: &[TestCase],
+) -> anyhow::Result<Vec<(String, anyhow::Error)>> {
+    let mut skipped = vec![];
+    for tc in testcases {
+        if let Err(err) = write_t8n(&Path::new(dir).join(&tc.funcname), tc) {
+            skipped.push((tc.source.to_owned(), err));
+        }
+    }
+    Ok(skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    // the example transaction of eip-155.
+    #[test]
+    fn sign_matches_eip155_example() {
+        let tx = Transaction {
+            nonce: U256::from(9),
+            gas_price: U256::from(20) * U256::exp10(9),
+            gas: U256::from(21000),
+            to: H160::from_str("3535353535353535353535353535353535353535").unwrap(),
+            value: U256::exp10(18),
+            data: vec![],
+            chain_id: Some(U256::one()),
+        };
+        let (v, r, s) = tx.sign_with(&"46".repeat(32)).unwrap();
+        assert_eq!(v, U256::from(37));
+        assert_eq!(
+            r,
+            H256::from_str("28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276").unwrap()
+        );
+        assert_eq!(
+            s,
+            H256::from_str("67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83").unwrap()
+        );
+    }
+}
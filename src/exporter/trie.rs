@@ -0,0 +1,143 @@
+use hash_db::Hasher;
+use plain_hasher::PlainHasher;
+use primitive_types::{H256, U256};
+use rlp::RlpStream;
+use sha3::{Digest, Keccak256};
+use std::collections::BTreeMap;
+use triehash::sec_trie_root;
+
+use crate::core::NormalizedAccount;
+use evm::backend::Log;
+
+struct KeccakHasher;
+
+impl Hasher for KeccakHasher {
+    type Out = H256;
+    type StdHasher = PlainHasher;
+    const LENGTH: usize = 32;
+
+    fn hash(data: &[u8]) -> H256 {
+        keccak(data)
+    }
+}
+
+pub(crate) fn keccak(data: &[u8]) -> H256 {
+    H256::from_slice(&Keccak256::digest(data))
+}
+
+fn storage_root(storage: &BTreeMap<H256, H256>) -> H256 {
+    let slots = storage
+        .iter()
+        .filter(|(_, value)| !value.is_zero())
+        .map(|(key, value)| (key, rlp::encode(&U256::from_big_endian(value.as_bytes()))));
+    sec_trie_root::<KeccakHasher, _, _, _>(slots)
+}
+
+// root of the secure merkle patricia trie of `[nonce, balance, storage root, code hash]` by
+// address.
+pub(crate) fn state_root(accounts: &[NormalizedAccount]) -> H256 {
+    let accounts = accounts.iter().map(|acct| {
+        let mut stream = RlpStream::new_list(4);
+        stream.append(&acct.nonce);
+        stream.append(&acct.balance);
+        stream.append(&storage_root(&acct.storage));
+        stream.append(&keccak(&acct.code));
+        (acct.address, stream.out())
+    });
+    sec_trie_root::<KeccakHasher, _, _, _>(accounts)
+}
+
+// keccak of the rlp list of `[address, topics, data]`, as in state test `logs`.
+pub(crate) fn logs_hash(logs: &[Log]) -> H256 {
+    let mut stream = RlpStream::new_list(logs.len());
+    for log in logs {
+        stream.begin_list(3);
+        stream.append(&log.address);
+        stream.append_list(&log.topics);
+        stream.append(&log.data);
+    }
+    keccak(&stream.out())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitive_types::H160;
+    use std::str::FromStr;
+
+    const EMPTY_ROOT: &str = "56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421";
+    const EMPTY_CODE_HASH: &str = "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470";
+
+    // root of a trie holding one entry: a leaf with the full hashed key as its path.
+    fn leaf_root(key: &[u8], value: &[u8]) -> H256 {
+        let mut path = vec![0x20];
+        path.extend_from_slice(keccak(key).as_bytes());
+        let mut stream = RlpStream::new_list(2);
+        stream.append(&path);
+        stream.append(&value.to_vec());
+        keccak(&stream.out())
+    }
+
+    fn account_rlp(balance: U256, storage_root: H256, code_hash: H256) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(4);
+        stream.append(&U256::zero());
+        stream.append(&balance);
+        stream.append(&storage_root);
+        stream.append(&code_hash);
+        stream.out().to_vec()
+    }
+
+    #[test]
+    fn empty_roots() {
+        assert_eq!(state_root(&[]), H256::from_str(EMPTY_ROOT).unwrap());
+        assert_eq!(keccak(&[]), H256::from_str(EMPTY_CODE_HASH).unwrap());
+    }
+
+    // the sender of ethereum/tests stExample/add11: 1 ether, no code, no storage.
+    #[test]
+    fn state_root_of_an_account() {
+        let address = H160::from_str("a94f5374fce5edbc8e2a8697c15331677e6ebf0b").unwrap();
+        let balance = U256::exp10(18);
+        let acct = NormalizedAccount {
+            address,
+            balance,
+            nonce: U256::zero(),
+            code: vec![],
+            storage: BTreeMap::new(),
+        };
+        let value = account_rlp(
+            balance,
+            H256::from_str(EMPTY_ROOT).unwrap(),
+            H256::from_str(EMPTY_CODE_HASH).unwrap(),
+        );
+        assert_eq!(state_root(&[acct]), leaf_root(address.as_bytes(), &value));
+    }
+
+    // the add11 contract after the test: slot 0 holds 2, zero slots are left out.
+    #[test]
+    fn state_root_of_a_contract() {
+        let address = H160::from_str("095e7baea6a6c7c4c2dfeb977efac326af552d87").unwrap();
+        let code = hex::decode("600160010160005500").unwrap();
+        let acct = NormalizedAccount {
+            address,
+            balance: U256::exp10(18),
+            nonce: U256::zero(),
+            code: code.clone(),
+            storage: BTreeMap::from([
+                (H256::zero(), H256::from_low_u64_be(2)),
+                (H256::from_low_u64_be(1), H256::zero()),
+            ]),
+        };
+        let storage_root = leaf_root(H256::zero().as_bytes(), &rlp::encode(&U256::from(2)));
+        let value = account_rlp(U256::exp10(18), storage_root, keccak(&code));
+        assert_eq!(state_root(&[acct]), leaf_root(address.as_bytes(), &value));
+    }
+
+    #[test]
+    fn empty_logs_hash() {
+        assert_eq!(
+            logs_hash(&[]),
+            H256::from_str("1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347").unwrap()
+        );
+    }
+}
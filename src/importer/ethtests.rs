@@ -0,0 +1,415 @@
+use anyhow::{anyhow, bail, Context};
+use primitive_types::U256;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::BufReader;
+
+use super::{strip_hex_prefix, to_u128, to_u64, to_word};
+use crate::core::{decode_hex, parse_address, parse_u256, AccountDeseriarizable, Env, ExpectConfig, ExpectedAccount, Input};
+use crate::mover::mover::move_identifier;
+
+// importer for the json fixtures of https://github.com/ethereum/tests.
+// GeneralStateTests (and the VMTests directory inside it) use the `transaction` + `post` layout,
+// the legacy VMTests use `exec`.
+
+// the key almost every filled test signs its transaction with, and its address.
+const TEST_SECRET_KEY: &str = "45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8";
+const TEST_SENDER: &str = "a94f5374fce5edbc8e2a8697c15331677e6ebf0b";
+
+#[derive(Deserialize, Debug)]
+struct EthTest {
+    env: EthEnv,
+    pre: BTreeMap<String, EthAccount>,
+    transaction: Option<EthTransaction>,
+    exec: Option<EthExec>,
+    #[serde(default)]
+    post: serde_json::Value,
+    // logs hash of vm tests, state tests have it per `post` entry.
+    logs: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct EthEnv {
+    current_coinbase: String,
+    current_difficulty: Option<String>,
+    current_gas_limit: String,
+    current_number: String,
+    current_timestamp: String,
+    current_base_fee: Option<String>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct EthAccount {
+    balance: String,
+    code: String,
+    nonce: String,
+    storage: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct EthTransaction {
+    data: Vec<String>,
+    gas_limit: Vec<String>,
+    gas_price: Option<String>,
+    max_fee_per_gas: Option<String>,
+    max_priority_fee_per_gas: Option<String>,
+    secret_key: Option<String>,
+    sender: Option<String>,
+    to: String,
+    value: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct EthExec {
+    address: String,
+    caller: String,
+    code: String,
+    data: String,
+    gas: String,
+    gas_price: String,
+    origin: String,
+    value: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct EthPost {
+    indexes: EthIndexes,
+    hash: String,
+    logs: String,
+    expect_exception: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct EthIndexes {
+    data: usize,
+    gas: usize,
+    value: usize,
+}
+
+// reads one fixture file. every test of the file expands into one input per fork and
+// (data, gas, value) index combination listed in its `post` section.
+pub(crate) fn read_ethtests(
+    path: &str,
+    fork: Option<&str>,
+) -> anyhow::Result<Vec<(String, anyhow::Result<Input>)>> {
+    let json_file = File::open(path)?;
+    let reader = BufReader::new(json_file);
+    let tests: BTreeMap<String, EthTest> = serde_json::from_reader(reader)?;
+
+    let mut inputs = vec![];
+    for (name, test) in &tests {
+        if let Some(exec) = &test.exec {
+            let id = move_identifier(name);
+            let fork = fork.unwrap_or("london");
+            inputs.push((id.to_owned(), to_vm_input(&id, test, exec, fork)));
+        } else if let Some(tx) = &test.transaction {
+            let post: BTreeMap<String, Vec<EthPost>> = serde_json::from_value(test.post.clone())?;
+            for (post_fork, entries) in &post {
+                if let Some(fork) = fork {
+                    if !fork.eq_ignore_ascii_case(post_fork) {
+                        continue;
+                    }
+                }
+                for entry in entries {
+                    let idx = &entry.indexes;
+                    let id = move_identifier(&format!(
+                        "{}_{}_d{}g{}v{}",
+                        name, post_fork, idx.data, idx.gas, idx.value
+                    ));
+                    inputs.push((id.to_owned(), to_state_input(&id, test, tx, post_fork, entry)));
+                }
+            }
+        } else {
+            inputs.push((name.to_owned(), Err(anyhow!("{}: neither `transaction` nor `exec`", name))));
+        }
+    }
+    Ok(inputs)
+}
+
+fn to_state_input(
+    id: &str,
+    test: &EthTest,
+    tx: &EthTransaction,
+    fork: &str,
+    post: &EthPost,
+) -> anyhow::Result<Input> {
+    if tx.to.is_empty() {
+        bail!("{}: contract creation transactions are not supported", id);
+    }
+    if let Some(exception) = &post.expect_exception {
+        bail!("{}: invalid transactions ({}) are not supported", id, exception);
+    }
+    let idx = &post.indexes;
+    let sender = match (&tx.sender, &tx.secret_key) {
+        (Some(sender), _) => parse_address(sender)?,
+        (None, Some(key)) if strip_hex_prefix(key) == TEST_SECRET_KEY => parse_address(TEST_SENDER)?,
+        _ => bail!("{}: cannot derive the sender, add `sender` to the transaction", id),
+    };
+    let to = parse_address(&tx.to)?;
+    let data = tx.data.get(idx.data).ok_or(anyhow!("{}: data index out of range", id))?;
+    let gas = tx.gas_limit.get(idx.gas).ok_or(anyhow!("{}: gas index out of range", id))?;
+    let value = tx.value.get(idx.value).ok_or(anyhow!("{}: value index out of range", id))?;
+
+    let mut env = to_env(&test.env, fork)?;
+    let gas_price = effective_gas_price(tx, env.block_base_fee_per_gas)
+        .with_context(|| format!("{}: invalid gas price", id))?;
+    env.caller = sender;
+    env.origin = sender;
+    env.address = to;
+    env.gas_limit = to_u64(parse_u256(gas)?)?;
+    env.gas_price = gas_price;
+
+    let code = test
+        .pre
+        .iter()
+        .find(|(address, _)| parse_address(address).ok() == Some(to))
+        .map(|(_, account)| decode_hex(&account.code))
+        .transpose()?
+        .unwrap_or_default();
+
+    let input = Input {
+        id: id.to_owned(),
+        code,
+        value: to_u128(parse_u256(value)?)?,
+        calldata: decode_hex(data)?,
+        accounts: to_accounts(&test.pre)?,
+        source_map: BTreeMap::new(),
+        env,
+        expect: Some(ExpectConfig {
+            state_root: Some(post.hash.to_owned()),
+            logs_hash: Some(post.logs.to_owned()),
+            ..ExpectConfig::default()
+        }),
+        names: BTreeMap::new(),
+        snapshot: None,
+    };
+    Ok(input)
+}
+
+fn to_vm_input(id: &str, test: &EthTest, exec: &EthExec, fork: &str) -> anyhow::Result<Input> {
+    let calldata = decode_hex(&exec.data)?;
+    let mut env = to_env(&test.env, fork)?;
+    env.caller = parse_address(&exec.caller)?;
+    env.origin = parse_address(&exec.origin)?;
+    env.address = parse_address(&exec.address)?;
+    env.gas_price = parse_u256(&exec.gas_price)?;
+    // vm tests run the code directly, the executor runs a transaction and charges its
+    // intrinsic cost first.
+    env.gas_limit = to_u64(parse_u256(&exec.gas)?)?.saturating_add(intrinsic_gas(&calldata, fork));
+
+    // vm tests have no post-state root, the accounts they change are compared instead. their
+    // call value is not transferred, so the balances are left out.
+    let post: BTreeMap<String, EthAccount> = if test.post.is_null() {
+        BTreeMap::new()
+    } else {
+        serde_json::from_value(test.post.clone())?
+    };
+    let mut accounts = vec![];
+    for (address, account) in &post {
+        if test.pre.get(address) == Some(account) {
+            continue;
+        }
+        let mut storage = HashMap::new();
+        for (key, value) in &account.storage {
+            storage.insert(to_word(key)?, to_word(value)?);
+        }
+        accounts.push(ExpectedAccount {
+            address: hex::encode(parse_address(address)?),
+            balance: None,
+            nonce: Some(to_u128(parse_u256(&account.nonce)?)?),
+            code: Some(hex::encode(decode_hex(&account.code)?)),
+            storage,
+        });
+    }
+    let expect = ExpectConfig {
+        accounts,
+        logs_hash: test.logs.to_owned(),
+        ..ExpectConfig::default()
+    };
+
+    let input = Input {
+        id: id.to_owned(),
+        code: decode_hex(&exec.code)?,
+        value: to_u128(parse_u256(&exec.value)?)?,
+        calldata,
+        accounts: to_accounts(&test.pre)?,
+        source_map: BTreeMap::new(),
+        env,
+        expect: Some(expect),
+        names: BTreeMap::new(),
+        snapshot: None,
+    };
+    Ok(input)
+}
+
+fn to_env(env: &EthEnv, fork: &str) -> anyhow::Result<Env> {
+    let block_difficulty = env
+        .current_difficulty
+        .as_ref()
+        .map(|difficulty| parse_u256(difficulty))
+        .transpose()?
+        .unwrap_or_default();
+    let block_base_fee_per_gas = env
+        .current_base_fee
+        .as_ref()
+        .map(|fee| parse_u256(fee))
+        .transpose()?
+        .unwrap_or_default();
+    let env = Env {
+        block_number: parse_u256(&env.current_number)?,
+        block_coinbase: parse_address(&env.current_coinbase)?,
+        block_timestamp: parse_u256(&env.current_timestamp)?,
+        block_difficulty,
+        block_gas_limit: parse_u256(&env.current_gas_limit)?,
+        block_base_fee_per_gas,
+        fork: fork.to_ascii_lowercase(),
+        ..Env::default()
+    };
+    Ok(env)
+}
+
+fn to_accounts(pre: &BTreeMap<String, EthAccount>) -> anyhow::Result<Vec<AccountDeseriarizable>> {
+    let mut accounts = vec![];
+    for (address, account) in pre {
+        let mut storage = BTreeMap::new();
+        for (key, value) in &account.storage {
+            storage.insert(to_word(key)?, to_word(value)?);
+        }
+        accounts.push(AccountDeseriarizable {
+            address: hex::encode(parse_address(address)?),
+            balance: to_u128(parse_u256(&account.balance)?)?,
+            nonce: to_u128(parse_u256(&account.nonce)?)?,
+            code: hex::encode(decode_hex(&account.code)?),
+            storage,
+        });
+    }
+    Ok(accounts)
+}
+
+// the gas price of legacy transactions, `min(maxFeePerGas, baseFee + maxPriorityFeePerGas)` of
+// eip-1559 transactions.
+fn effective_gas_price(tx: &EthTransaction, base_fee: U256) -> anyhow::Result<U256> {
+    match (&tx.gas_price, &tx.max_fee_per_gas, &tx.max_priority_fee_per_gas) {
+        (Some(gas_price), _, _) => parse_u256(gas_price),
+        (None, Some(max_fee), Some(max_priority_fee)) => Ok(std::cmp::min(
+            parse_u256(max_fee)?,
+            base_fee + parse_u256(max_priority_fee)?,
+        )),
+        _ => bail!("neither `gasPrice` nor `maxFeePerGas` and `maxPriorityFeePerGas`"),
+    }
+}
+
+// calldata costs 16 per non-zero byte from istanbul on (eip-2028), 68 before.
+fn intrinsic_gas(calldata: &[u8], fork: &str) -> u64 {
+    let non_zero_gas = if fork.eq_ignore_ascii_case("frontier") { 68 } else { 16 };
+    let data_gas: u64 = calldata.iter().map(|b| if *b == 0 { 4 } else { non_zero_gas }).sum();
+    21000 + data_gas
+}
+
+// artifact group name for a fixture file, e.g. `ethtests_addmod` for `.../addmod.json`.
+pub(crate) fn group_name(path: &str) -> String {
+    let stem = std::path::Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    move_identifier(&format!("ethtests_{}", stem))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(gas_price: Option<&str>, max_fee: Option<&str>, max_priority_fee: Option<&str>) -> EthTransaction {
+        EthTransaction {
+            data: vec![],
+            gas_limit: vec![],
+            gas_price: gas_price.map(str::to_owned),
+            max_fee_per_gas: max_fee.map(str::to_owned),
+            max_priority_fee_per_gas: max_priority_fee.map(str::to_owned),
+            secret_key: None,
+            sender: None,
+            to: "".to_owned(),
+            value: vec![],
+        }
+    }
+
+    #[test]
+    fn intrinsic_gas_prices_calldata_by_fork() {
+        let calldata = [0x00, 0x01, 0x02];
+        assert_eq!(intrinsic_gas(&calldata, "frontier"), 21000 + 4 + 68 * 2);
+        assert_eq!(intrinsic_gas(&calldata, "istanbul"), 21000 + 4 + 16 * 2);
+        assert_eq!(intrinsic_gas(&calldata, "london"), 21000 + 4 + 16 * 2);
+        assert_eq!(intrinsic_gas(&[], "berlin"), 21000);
+    }
+
+    #[test]
+    fn effective_gas_price_caps_the_priority_fee() {
+        let base_fee = U256::from(10);
+        let legacy = transaction(Some("0x0a"), None, None);
+        assert_eq!(effective_gas_price(&legacy, base_fee).unwrap(), U256::from(10));
+        let capped = transaction(None, Some("0x0c"), Some("0x05"));
+        assert_eq!(effective_gas_price(&capped, base_fee).unwrap(), U256::from(12));
+        let tipped = transaction(None, Some("0x20"), Some("0x02"));
+        assert_eq!(effective_gas_price(&tipped, base_fee).unwrap(), U256::from(12));
+        assert!(effective_gas_price(&transaction(None, Some("0x20"), None), base_fee).is_err());
+    }
+
+    #[test]
+    fn state_tests_expect_the_post_hash_and_logs() {
+        let fixture = r#"{
+            "add": {
+                "env": {
+                    "currentCoinbase": "0x2adc25665018aa1fe0e6bc666dac8fc2697ff9ba",
+                    "currentDifficulty": "0x020000",
+                    "currentGasLimit": "0xff112233445566",
+                    "currentNumber": "0x01",
+                    "currentTimestamp": "0x03e8",
+                    "currentBaseFee": "0x0a"
+                },
+                "pre": {
+                    "0x095e7baea6a6c7c4c2dfeb977efac326af552d87": {
+                        "balance": "0x0de0b6b3a7640000", "code": "0x600160010160005500", "nonce": "0x00", "storage": {}
+                    },
+                    "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b": {
+                        "balance": "0x0de0b6b3a7640000", "code": "0x", "nonce": "0x00", "storage": {}
+                    }
+                },
+                "transaction": {
+                    "data": ["0x"], "gasLimit": ["0x061a80"], "maxFeePerGas": "0x0c", "maxPriorityFeePerGas": "0x05",
+                    "nonce": "0x00", "secretKey": "0x45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8",
+                    "to": "0x095e7baea6a6c7c4c2dfeb977efac326af552d87", "value": ["0x01"]
+                },
+                "post": {
+                    "London": [{
+                        "hash": "0x01", "logs": "0x02", "indexes": { "data": 0, "gas": 0, "value": 0 }
+                    }],
+                    "Berlin": [{
+                        "hash": "0x03", "logs": "0x04", "indexes": { "data": 0, "gas": 0, "value": 0 },
+                        "expectException": "TR_TypeNotSupported"
+                    }]
+                }
+            }
+        }"#;
+        let path = std::env::temp_dir().join("evm-tester-ethtests-add.json");
+        std::fs::write(&path, fixture).unwrap();
+        let inputs = read_ethtests(&path.display().to_string(), None).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(inputs.len(), 2);
+        let (id, input) = &inputs[0];
+        assert_eq!(id, "add_Berlin_d0g0v0");
+        assert!(input.is_err());
+        let (id, input) = &inputs[1];
+        assert_eq!(id, "add_London_d0g0v0");
+        let input = input.as_ref().unwrap();
+        assert_eq!(input.env.gas_price, U256::from(12));
+        let expect = input.expect.as_ref().unwrap();
+        assert_eq!(expect.state_root.as_deref(), Some("0x01"));
+        assert_eq!(expect.logs_hash.as_deref(), Some("0x02"));
+    }
+}
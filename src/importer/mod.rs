@@ -0,0 +1,57 @@
+use anyhow::bail;
+use primitive_types::U256;
+
+use crate::core::decode_hex;
+
+pub(crate) mod ethtests;
+pub(crate) mod prestate;
+pub(crate) mod t8n;
+
+// helpers shared by the importers. external dumps use 0x-prefixed, possibly odd length hex.
+
+pub(super) fn strip_hex_prefix(value: &str) -> &str {
+    value.trim_start_matches("0x")
+}
+
+// storage keys and values as the even length hex strings `AccountDeseriarizable` expects.
+pub(super) fn to_word(value: &str) -> anyhow::Result<String> {
+    let word = hex::encode(decode_hex(value)?);
+    if word.is_empty() {
+        return Ok("00".to_owned());
+    }
+    Ok(word)
+}
+
+pub(super) fn to_u128(value: U256) -> anyhow::Result<u128> {
+    if value > U256::from(u128::MAX) {
+        bail!("{} does not fit in u128", value);
+    }
+    Ok(value.as_u128())
+}
+
+pub(super) fn to_u64(value: U256) -> anyhow::Result<u64> {
+    if value > U256::from(u64::MAX) {
+        bail!("{} does not fit in u64", value);
+    }
+    Ok(value.as_u64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_word_pads_to_even_length() {
+        assert_eq!(to_word("0x").unwrap(), "00");
+        assert_eq!(to_word("0x1").unwrap(), "01");
+        assert_eq!(to_word("0x0100").unwrap(), "0100");
+    }
+
+    #[test]
+    fn narrowing_rejects_overflow() {
+        assert_eq!(to_u64(U256::from(u64::MAX)).unwrap(), u64::MAX);
+        assert!(to_u64(U256::from(u64::MAX) + 1).is_err());
+        assert_eq!(to_u128(U256::from(u128::MAX)).unwrap(), u128::MAX);
+        assert!(to_u128(U256::from(u128::MAX) + 1).is_err());
+    }
+}
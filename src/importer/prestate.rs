@@ -0,0 +1,298 @@
+use anyhow::bail;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+use super::{to_u128, to_u64, to_word};
+use crate::core::{decode_hex, parse_address, parse_u256, AccountDeseriarizable, EnvConfig, ExpectConfig, ExpectedAccount, HuffConfig, StateConfig};
+use crate::mover::mover::move_identifier;
+use crate::reader::find_config;
+
+// a mainnet transaction together with geth's `prestateTracer` output for it.
+//
+// {
+//   "transaction": <eth_getTransactionByHash result>,
+//   "block": <eth_getBlockByNumber result, optional>,
+//   "prestate": <debug_traceTransaction with {"tracer": "prestateTracer"}>,
+//   "diff": <debug_traceTransaction with {"tracer": "prestateTracer", "tracerConfig": {"diffMode": true}}, optional>
+// }
+#[derive(Deserialize, Debug)]
+struct PrestateDump {
+    transaction: DumpTransaction,
+    block: Option<DumpBlock>,
+    prestate: BTreeMap<String, DumpAccount>,
+    diff: Option<DumpDiff>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct DumpTransaction {
+    hash: Option<String>,
+    from: String,
+    to: Option<String>,
+    value: Option<String>,
+    input: String,
+    gas: Option<String>,
+    gas_price: Option<String>,
+    chain_id: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct DumpBlock {
+    number: Option<String>,
+    timestamp: Option<String>,
+    miner: Option<String>,
+    gas_limit: Option<String>,
+    base_fee_per_gas: Option<String>,
+    difficulty: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct DumpAccount {
+    balance: Option<String>,
+    nonce: Option<u64>,
+    code: Option<String>,
+    storage: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DumpDiff {
+    pre: BTreeMap<String, DumpAccount>,
+    post: BTreeMap<String, DumpAccount>,
+}
+
+// converts a prestate dump into a stateful test `<group_dir>/<id>/state.json` with the target
+// code in `<id>.bytecode` next to it.
+pub(crate) fn import_prestate(path: &str, group_dir: &str, id: Option<&str>) -> anyhow::Result<String> {
+    let json_file = File::open(path)?;
+    let reader = BufReader::new(json_file);
+    let dump: PrestateDump = serde_json::from_reader(reader)?;
+
+    let tx = &dump.transaction;
+    let to = match &tx.to {
+        Some(to) => parse_address(to)?,
+        None => bail!("{}: contract creation transactions are not supported", path),
+    };
+    let from = parse_address(&tx.from)?;
+    let id = match (id, &tx.hash) {
+        (Some(id), _) => move_identifier(id),
+        (None, Some(hash)) => {
+            let hash = hex::encode(decode_hex(hash)?);
+            format!("tx_{}", &hash[..8.min(hash.len())])
+        }
+        (None, None) => bail!("{}: no transaction hash, pass an id", path),
+    };
+
+    let test_dir = Path::new(group_dir).join(&id);
+    if test_dir.exists() {
+        bail!("{} already exists", test_dir.display());
+    }
+    if find_config(Path::new(group_dir), "testcase").is_none() {
+        bail!("{} is not a test group (no testcase.json, .yaml or .toml)", group_dir);
+    }
+
+    let mut code = vec![];
+    let mut accounts = vec![];
+    for (address, account) in &dump.prestate {
+        let mut account = to_account(address, account)?;
+        // the target code lives in the bytecode file.
+        if parse_address(address)? == to {
+            code = hex::decode(&account.code)?;
+            account.code = "".to_owned();
+        }
+        accounts.push(account);
+    }
+
+    let mut env = EnvConfig {
+        caller: Some(hex::encode(from)),
+        address: Some(hex::encode(to)),
+        origin: Some(hex::encode(from)),
+        gas_limit: tx.gas.as_ref().map(|gas| parse_u256(gas).and_then(to_u64)).transpose()?,
+        gas_price: tx.gas_price.as_ref().map(|price| parse_u256(price).and_then(to_u128)).transpose()?,
+        chain_id: tx.chain_id.as_ref().map(|id| parse_u256(id).and_then(to_u128)).transpose()?,
+        ..EnvConfig::default()
+    };
+    let mut coinbase = None;
+    if let Some(block) = &dump.block {
+        let number = |value: &Option<String>| value.as_ref().map(|v| parse_u256(v).and_then(to_u128)).transpose();
+        env.block_number = number(&block.number)?;
+        env.timestamp = number(&block.timestamp)?;
+        env.block_gas_limit = number(&block.gas_limit)?;
+        env.base_fee = number(&block.base_fee_per_gas)?;
+        env.difficulty = number(&block.difficulty)?;
+        if let Some(miner) = &block.miner {
+            let miner = parse_address(miner)?;
+            coinbase = Some(miner);
+            env.coinbase = Some(hex::encode(miner));
+        }
+    }
+
+    let expect = match &dump.diff {
+        Some(diff) => {
+            let mut expected = vec![];
+            for (address, post) in &diff.post {
+                let pre = diff.pre.get(address);
+                let address = parse_address(address)?;
+                // the executor charges no gas fee and pays no miner reward, so the balances of the
+                // sender and the miner in `diff.post` cannot match and are deliberately not
+                // expected. their nonce, code and storage still are.
+                let balance = if address == from || Some(address) == coinbase {
+                    None
+                } else {
+                    post.balance.as_ref().map(|b| parse_u256(b).and_then(to_u128)).transpose()?
+                };
+                let mut storage = HashMap::new();
+                if let Some(slots) = pre.and_then(|pre| pre.storage.as_ref()) {
+                    // cleared slots are only listed in `pre`.
+                    for key in slots.keys() {
+                        storage.insert(to_word(key)?, "00".to_owned());
+                    }
+                }
+                for (key, value) in post.storage.iter().flatten() {
+                    storage.insert(to_word(key)?, to_word(value)?);
+                }
+                expected.push(ExpectedAccount {
+                    address: hex::encode(address),
+                    balance,
+                    nonce: post.nonce.map(u128::from),
+                    code: post.code.as_ref().map(|code| decode_hex(code).map(hex::encode)).transpose()?,
+                    storage,
+                });
+            }
+            Some(ExpectConfig {
+                accounts: expected,
+                ..ExpectConfig::default()
+            })
+        }
+        None => None,
+    };
+
+    let value = tx.value.as_ref().map(|value| parse_u256(value).and_then(to_u128)).transpose()?;
+    let filename = format!("{}.bytecode", id);
+    let state_config = StateConfig {
+        id: id.to_owned(),
+        names: vec![],
+        filename: filename.to_owned(),
+        filetype: "bytecode".to_owned(),
+        code: None,
+        asm: None,
+        value: value.unwrap_or_default(),
+        calldata: hex::encode(decode_hex(&tx.input)?),
+        fixtures: vec![],
+        accounts,
+        cases: vec![],
+        snapshot: None,
+        huff: HuffConfig::default(),
+        env,
+        expect,
+    };
+
+    fs::create_dir_all(&test_dir)?;
+    fs::write(test_dir.join(&filename), hex::encode(&code))?;
+    let text = serde_json::to_string_pretty(&state_config)?;
+    let state_path = test_dir.join("state.json");
+    fs::write(&state_path, text)?;
+    Ok(state_path.display().to_string())
+}
+
+fn to_account(address: &str, account: &DumpAccount) -> anyhow::Result<AccountDeseriarizable> {
+    let mut storage = BTreeMap::new();
+    for (key, value) in account.storage.iter().flatten() {
+        storage.insert(to_word(key)?, to_word(value)?);
+    }
+    let balance = match &account.balance {
+        Some(balance) => to_u128(parse_u256(balance)?)?,
+        None => 0,
+    };
+    let code = match &account.code {
+        Some(code) => hex::encode(decode_hex(code)?),
+        None => "".to_owned(),
+    };
+    Ok(AccountDeseriarizable {
+        address: hex::encode(parse_address(address)?),
+        balance,
+        nonce: account.nonce.unwrap_or_default() as u128,
+        code,
+        storage,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imports_a_prestate_dump_with_its_diff() {
+        let dir = std::env::temp_dir().join(format!("evm-tester-prestate-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("testcase.json"), r#"{"name": "prestate"}"#).unwrap();
+        let sender = "0x00000000000000000000000000000000000000aa";
+        let target = "0x00000000000000000000000000000000000000bb";
+        let miner = "0x00000000000000000000000000000000000000cc";
+        let dump = format!(
+            r#"{{
+                "transaction": {{"hash": "0x1234567890abcdef", "from": "{sender}", "to": "{target}", "value": "0x0", "input": "0x01", "gas": "0x5208"}},
+                "block": {{"number": "0x10", "miner": "{miner}"}},
+                "prestate": {{
+                    "{sender}": {{"balance": "0x64", "nonce": 1}},
+                    "{target}": {{"balance": "0x0", "code": "0x600160005500", "storage": {{"0x1": "0x2"}}}}
+                }},
+                "diff": {{
+                    "pre": {{
+                        "{sender}": {{"balance": "0x64", "nonce": 1}},
+                        "{target}": {{"storage": {{"0x1": "0x2"}}}},
+                        "{miner}": {{"balance": "0x0"}}
+                    }},
+                    "post": {{
+                        "{sender}": {{"balance": "0x10", "nonce": 2}},
+                        "{target}": {{"balance": "0x5", "storage": {{"0x0": "0x1"}}}},
+                        "{miner}": {{"balance": "0x20"}}
+                    }}
+                }}
+            }}"#
+        );
+        let dump_path = dir.join("dump.json");
+        fs::write(&dump_path, dump).unwrap();
+
+        let group_dir = dir.display().to_string();
+        let state_path = import_prestate(&dump_path.display().to_string(), &group_dir, None).unwrap();
+        assert_eq!(state_path, dir.join("tx_12345678/state.json").display().to_string());
+        let bytecode = fs::read_to_string(dir.join("tx_12345678/tx_12345678.bytecode")).unwrap();
+        assert_eq!(bytecode, "600160005500");
+
+        let state: StateConfig = serde_json::from_str(&fs::read_to_string(&state_path).unwrap()).unwrap();
+        assert_eq!(state.filename, "tx_12345678.bytecode");
+        assert_eq!(state.calldata, "01");
+        assert_eq!(state.env.block_number, Some(16));
+        assert_eq!(state.env.coinbase.as_deref(), Some(&miner[2..]));
+        // the target code is only in the bytecode file.
+        let code: Vec<&str> = state.accounts.iter().map(|acct| acct.code.as_str()).collect();
+        assert_eq!(code, ["", ""]);
+
+        let expect = state.expect.unwrap();
+        let account = |address: &str| {
+            expect
+                .accounts
+                .iter()
+                .find(|acct| acct.address == address[2..])
+                .unwrap()
+        };
+        // the sender and the miner pay and get no fees, their balances are not expected.
+        assert_eq!(account(sender).balance, None);
+        assert_eq!(account(sender).nonce, Some(2));
+        assert_eq!(account(miner).balance, None);
+        let target = account(target);
+        assert_eq!(target.balance, Some(5));
+        // slot 01 is only in `pre`, it was cleared.
+        let storage = BTreeMap::from_iter(target.storage.clone());
+        assert_eq!(
+            storage,
+            BTreeMap::from([("00".to_owned(), "01".to_owned()), ("01".to_owned(), "00".to_owned())])
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
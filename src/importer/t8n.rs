@@ -0,0 +1,81 @@
+use anyhow::{anyhow, Context};
+use primitive_types::H160;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::core::{parse_address, NormalizedAccount};
+use crate::executor::snapshot::to_account;
+
+fn read_json(path: &Path) -> anyhow::Result<Value> {
+    let data = fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+    serde_json::from_str(&data).with_context(|| format!("invalid {}", path.display()))
+}
+
+fn read_alloc(alloc: &Value) -> anyhow::Result<BTreeMap<H160, NormalizedAccount>> {
+    let alloc = alloc.as_object().ok_or(anyhow!("alloc is not an object"))?;
+    let mut accounts = BTreeMap::new();
+    for (address, entry) in alloc {
+        let address = parse_address(address)?;
+        accounts.insert(address, to_account(address, entry)?);
+    }
+    Ok(accounts)
+}
+
+fn compare_hash(expected: &Value, result: &Value, name: &str, differences: &mut Vec<String>) {
+    let expected = expected[name].as_str().unwrap_or_default();
+    let actual = result[name].as_str().unwrap_or_default();
+    if !expected.eq_ignore_ascii_case(actual) {
+        differences.push(format!("{} {}, t8n {}", name, expected, actual));
+    }
+}
+
+// compares the t8n output in `<test dir>/out` (`result.json` and the post `alloc.json`) with
+// `expected.json` written by the t8n export. returns the differences.
+pub(crate) fn compare_t8n(test_dir: &Path) -> anyhow::Result<Vec<String>> {
+    let expected = read_json(&test_dir.join("expected.json"))?;
+    let result = read_json(&test_dir.join("out").join("result.json"))?;
+    let post = read_alloc(&read_json(&test_dir.join("out").join("alloc.json"))?)?;
+
+    let mut differences = vec![];
+    if let Some(rejected) = result["rejected"].as_array() {
+        for tx in rejected {
+            differences.push(format!("transaction rejected: {}", tx["error"]));
+        }
+    }
+    compare_hash(&expected, &result, "stateRoot", &mut differences);
+    compare_hash(&expected, &result, "logsHash", &mut differences);
+
+    let empty = Value::Object(Map::new());
+    for (address, acct) in read_alloc(&expected["accounts"])? {
+        let actual = match post.get(&address) {
+            Some(actual) => actual.clone(),
+            None => to_account(address, &empty)?,
+        };
+        if acct.balance != actual.balance {
+            differences.push(format!("{:?}: balance {}, t8n {}", address, acct.balance, actual.balance));
+        }
+        if acct.nonce != actual.nonce {
+            differences.push(format!("{:?}: nonce {}, t8n {}", address, acct.nonce, actual.nonce));
+        }
+        if acct.code != actual.code {
+            differences.push(format!(
+                "{:?}: code 0x{}, t8n 0x{}",
+                address,
+                hex::encode(&acct.code),
+                hex::encode(&actual.code)
+            ));
+        }
+        for (key, value) in &acct.storage {
+            let actual_value = actual.storage.get(key).cloned().unwrap_or_default();
+            if *value != actual_value {
+                differences.push(format!(
+                    "{:?}: storage {:?} {:?}, t8n {:?}",
+                    address, key, value, actual_value
+                ));
+            }
+        }
+    }
+    Ok(differences)
+}
@@ -0,0 +1,501 @@
+use anyhow::anyhow;
+use jsonschema::{Draft, JSONSchema};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::{
+    AccountDeseriarizable, FileType, NormalizedAccount, PackageConfig, StateConfig, StatelessConfig,
+    TestGroupConfig,
+};
+use crate::reader::{find_config, group_code_files, parse_file_type, read_config_value, read_stateless_text, ConfigText, CONFIG_EXTENSIONS};
+
+// published schema of testcase, state, stateless and package configs.
+const SCHEMA: &str = include_str!("../../schema/config.schema.json");
+
+// extensions of the stateless test sources picked up by `generate`.
+const SOURCE_EXTENSIONS: [&str; 3] = ["huff", "bytecode", "easm"];
+
+pub(crate) struct Problem {
+    pub path: String,
+    pub message: String,
+}
+
+struct Linter {
+    schemas: BTreeMap<&'static str, JSONSchema>,
+    problems: Vec<Problem>,
+}
+
+// walks the resources tree and collects every problem instead of stopping at the first one.
+pub(crate) fn lint(root: &str) -> anyhow::Result<Vec<Problem>> {
+    let schema: Value = serde_json::from_str(SCHEMA)?;
+    let mut schemas = BTreeMap::new();
+    for kind in ["testcase", "state", "stateless", "package"] {
+        let wrapper = json!({
+            "definitions": schema["definitions"],
+            "allOf": [{ "$ref": format!("#/definitions/{}", kind) }],
+        });
+        let compiled = JSONSchema::options()
+            .with_draft(Draft::Draft7)
+            .compile(&wrapper)
+            .map_err(|err| anyhow!("invalid schema: {}", err))?;
+        schemas.insert(kind, compiled);
+    }
+
+    let mut linter = Linter {
+        schemas,
+        problems: vec![],
+    };
+    let package = linter.find_config(Path::new(root), "package");
+    if let Some(path) = &package {
+        let _: Option<PackageConfig> = linter.read(path, "package", &[]);
+    }
+    // groups at any depth, like `generate`.
+    let mut groups = BTreeSet::new();
+    group_dirs(Path::new(root), &mut groups)?;
+    if !groups.contains(Path::new(root)) {
+        for path in list_dir(Path::new(root))? {
+            if package.as_ref() == Some(&path) {
+                continue;
+            }
+            if !path.is_dir() {
+                linter.report(&path, "stray file outside a test group");
+            } else if !groups.iter().any(|group| group.starts_with(&path)) {
+                linter.report(&path, "directory without a testcase.json (or .yaml, .toml)");
+            }
+        }
+    }
+    for dir in &groups {
+        linter.lint_group(dir)?;
+    }
+    Ok(linter.problems)
+}
+
+// directories holding a testcase config, `dir` included.
+fn group_dirs(dir: &Path, groups: &mut BTreeSet<PathBuf>) -> anyhow::Result<()> {
+    if find_config(dir, "testcase").is_some() {
+        groups.insert(dir.to_owned());
+    }
+    for path in list_dir(dir)? {
+        if path.is_dir() {
+            group_dirs(&path, groups)?;
+        }
+    }
+    Ok(())
+}
+
+impl Linter {
+    fn report(&mut self, path: &Path, message: impl Into<String>) {
+        self.problems.push(Problem {
+            path: path.display().to_string(),
+            message: message.into(),
+        });
+    }
+
+    // validates a config value against the schema of its kind and deserializes it.
+    fn check<T: DeserializeOwned>(&mut self, path: &Path, kind: &str, config: &ConfigText) -> Option<T> {
+        let value = match config.value() {
+            Ok(value) => value,
+            Err(err) => {
+                self.report(path, format!("{:#}", err));
+                return None;
+            }
+        };
+        let errors: Vec<String> = match self.schemas[kind].validate(&value) {
+            Ok(()) => vec![],
+            Err(errors) => errors
+                .map(|err| {
+                    let pointer = err.instance_path.to_string();
+                    let pointer = if pointer.is_empty() {
+                        "/".to_owned()
+                    } else {
+                        pointer
+                    };
+                    format!("{}: {}", pointer, err)
+                })
+                .collect(),
+        };
+        if !errors.is_empty() {
+            for err in errors {
+                self.report(path, err);
+            }
+            return None;
+        }
+        match config.parse() {
+            Ok(config) => Some(config),
+            Err(err) => {
+                self.report(path, format!("{:#}", err));
+                None
+            }
+        }
+    }
+
+    fn read<T: DeserializeOwned>(
+        &mut self,
+        path: &Path,
+        kind: &str,
+        names: &[String],
+    ) -> Option<T> {
+        match ConfigText::read(path, names) {
+            Ok(config) => self.check(path, kind, &config),
+            Err(err) => {
+                self.report(path, format!("{:#}", err));
+                None
+            }
+        }
+    }
+
+    // the single `<stem>.<ext>` config of a directory. reports duplicates.
+    fn find_config(&mut self, dir: &Path, stem: &str) -> Option<PathBuf> {
+        let found: Vec<PathBuf> = CONFIG_EXTENSIONS
+            .iter()
+            .map(|ext| dir.join(format!("{}.{}", stem, ext)))
+            .filter(|path| path.is_file())
+            .collect();
+        if found.len() > 1 {
+            self.report(
+                dir,
+                format!(
+                    "multiple {} configs, only {} is used",
+                    stem,
+                    found[0].display()
+                ),
+            );
+        }
+        found.into_iter().next()
+    }
+
+    fn lint_group(&mut self, dir: &Path) -> anyhow::Result<()> {
+        let mut group = TestGroupConfig::default();
+        if let Some(path) = self.find_config(dir, "testcase") {
+            let config: Option<TestGroupConfig> = self.read(&path, "testcase", &[]);
+            group = config.unwrap_or_default();
+            group.code_files = group_code_files(dir, &group);
+            self.lint_accounts(&path, &group.accounts);
+            let fixtures: Vec<AccountDeseriarizable> = group.fixtures.values().cloned().collect();
+            self.lint_accounts(&path, &fixtures);
+        }
+
+        // test id to the path defining it.
+        let mut ids: BTreeMap<String, PathBuf> = BTreeMap::new();
+        let snapshots = referenced_snapshots(dir)?;
+        let entries = list_dir(dir)?;
+        for path in &entries {
+            if is_snapshot(&snapshots, path) {
+                continue;
+            }
+            if path.is_dir() {
+                // nested groups are linted on their own. directories without configs hold shared
+                // sources, e.g. for `include_paths`, and are skipped like `generate` does.
+                if find_config(path, "testcase").is_some() || !has_config(path)? {
+                    continue;
+                }
+                for (id, state_path) in self.lint_state(path, &group, &snapshots)? {
+                    self.add_id(&mut ids, id, state_path);
+                }
+                continue;
+            }
+            // checked with the accounts naming them.
+            if group.is_code_file(path) {
+                continue;
+            }
+            let stem = file_stem(path);
+            match extension(path).as_str() {
+                ext if SOURCE_EXTENSIONS.contains(&ext) => {
+                    self.lint_stateless(path, &group);
+                    self.add_id(&mut ids, stem, path.to_owned());
+                }
+                ext if CONFIG_EXTENSIONS.contains(&ext) => {
+                    let has_source = SOURCE_EXTENSIONS
+                        .iter()
+                        .any(|ext| dir.join(format!("{}.{}", stem, ext)).is_file());
+                    if stem != "testcase" && !has_source {
+                        self.report(
+                            path,
+                            "config without a matching .huff, .bytecode or .easm source",
+                        );
+                    }
+                }
+                ext => self.report(path, format!("unrecognized extension {:?}", ext)),
+            }
+        }
+        Ok(())
+    }
+
+    fn add_id(&mut self, ids: &mut BTreeMap<String, PathBuf>, id: String, path: PathBuf) {
+        match ids.get(&id) {
+            Some(first) => {
+                let message = format!(
+                    "duplicate test id {:?}, also defined by {}",
+                    id,
+                    first.display()
+                );
+                self.report(&path, message);
+            }
+            None => {
+                ids.insert(id, path);
+            }
+        }
+    }
+
+    fn lint_stateless(&mut self, path: &Path, group: &TestGroupConfig) {
+        let filepath = path.display().to_string();
+        match read_stateless_text(&filepath, group) {
+            Ok(text) => {
+                let origin = PathBuf::from(&text.origin);
+                let config: Option<StatelessConfig> = self.check(&origin, "stateless", &text);
+                if let Some(config) = config {
+                    self.lint_fixtures(&origin, group, &config.fixtures);
+                    self.lint_accounts(path, &config.accounts);
+                }
+            }
+            Err(err) => self.report(path, format!("{:#}", err)),
+        }
+    }
+
+    // returns the ids of the tests a state directory defines.
+    fn lint_state(
+        &mut self,
+        dir: &Path,
+        group: &TestGroupConfig,
+        snapshots: &BTreeSet<PathBuf>,
+    ) -> anyhow::Result<Vec<(String, PathBuf)>> {
+        for path in list_dir(dir)? {
+            let ext = extension(&path);
+            let is_state = file_stem(&path) == "state" && CONFIG_EXTENSIONS.contains(&ext.as_str());
+            if is_snapshot(snapshots, &path) {
+                continue;
+            }
+            if path.is_dir() || !(is_state || SOURCE_EXTENSIONS.contains(&ext.as_str())) {
+                self.report(&path, "stray file in a stateful test directory");
+            }
+        }
+        let path = match self.find_config(dir, "state") {
+            Some(path) => path,
+            None => {
+                self.report(dir, "directory without state.json (or .yaml, .toml)");
+                return Ok(vec![]);
+            }
+        };
+        let config: StateConfig = match self.read(&path, "state", &group.names) {
+            Some(config) => config,
+            None => return Ok(vec![]),
+        };
+
+        if config.code.is_none() && config.asm.is_none() {
+            let filepath = dir.join(&config.filename);
+            if !filepath.is_file() {
+                self.report(
+                    &path,
+                    format!("filename {:?} does not exist", config.filename),
+                );
+            }
+            let filetype = if config.filetype.is_empty() {
+                extension(&filepath)
+            } else {
+                config.filetype.to_owned()
+            };
+            match parse_file_type(&filetype) {
+                Ok(FileType::Solidity) => self.report(&path, "solidity sources are not supported"),
+                Ok(_) => {}
+                Err(err) => self.report(&path, err.to_string()),
+            }
+        }
+        if let Some(snapshot) = &config.snapshot {
+            if !dir.join(snapshot).exists() {
+                self.report(&path, format!("snapshot {:?} does not exist", snapshot));
+            }
+        }
+        self.lint_fixtures(&path, group, &config.fixtures);
+        self.lint_accounts(&path, &config.accounts);
+        for case in &config.cases {
+            self.lint_accounts(&path, &case.accounts);
+        }
+
+        let ids = if config.cases.is_empty() {
+            vec![config.id.to_owned()]
+        } else {
+            config
+                .cases
+                .iter()
+                .map(|case| format!("{}_{}", config.id, case.id))
+                .collect()
+        };
+        Ok(ids.into_iter().map(|id| (id, path.to_owned())).collect())
+    }
+
+    fn lint_fixtures(&mut self, path: &Path, group: &TestGroupConfig, fixtures: &[String]) {
+        for name in fixtures {
+            if !group.fixtures.contains_key(name) {
+                self.report(
+                    path,
+                    format!("fixture {:?} is not defined in the testcase config", name),
+                );
+            }
+        }
+    }
+
+    // account code given as a file must exist and have a known type. files are relative to the
+    // config at `path`.
+    fn lint_accounts(&mut self, path: &Path, accounts: &[AccountDeseriarizable]) {
+        let dir = path.parent().unwrap_or(Path::new("."));
+        for acct in accounts {
+            let code = Path::new(&acct.code);
+            if code.extension().is_none() {
+                // the schema allows values `generate` can not convert, e.g. odd length code.
+                if let Err(err) = NormalizedAccount::try_from(acct) {
+                    self.report(path, format!("account {}: {:#}", acct.address, err));
+                }
+                continue;
+            }
+            let filepath = dir.join(code);
+            if !filepath.is_file() {
+                self.report(path, format!("account code {:?} does not exist", acct.code));
+            } else if let Err(err) = parse_file_type(&extension(code)) {
+                self.report(&filepath, err.to_string());
+            }
+        }
+    }
+}
+
+// snapshots referenced by the state configs of a group. they are not test files.
+fn referenced_snapshots(group_dir: &Path) -> anyhow::Result<BTreeSet<PathBuf>> {
+    let mut snapshots = BTreeSet::new();
+    for dir in list_dir(group_dir)? {
+        let state = CONFIG_EXTENSIONS
+            .iter()
+            .map(|ext| dir.join(format!("state.{}", ext)))
+            .find(|path| path.is_file());
+        let value = match state.map(|path| read_config_value(&path)) {
+            Some(Ok(value)) => value,
+            _ => continue,
+        };
+        if let Some(snapshot) = value["snapshot"].as_str() {
+            if let Ok(path) = dir.join(snapshot).canonicalize() {
+                snapshots.insert(path);
+            }
+        }
+    }
+    Ok(snapshots)
+}
+
+fn is_snapshot(snapshots: &BTreeSet<PathBuf>, path: &Path) -> bool {
+    path.canonicalize().is_ok_and(|path| snapshots.contains(&path))
+}
+
+// entries of a directory in a stable order, without hidden files.
+fn has_config(dir: &Path) -> anyhow::Result<bool> {
+    Ok(list_dir(dir)?
+        .iter()
+        .any(|path| path.is_file() && CONFIG_EXTENSIONS.contains(&extension(path).as_str())))
+}
+
+fn list_dir(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut entries = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !file_name(&path).starts_with('.') {
+            entries.push(path);
+        }
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(address: &str, code: &str, storage: &[(&str, &str)]) -> AccountDeseriarizable {
+        AccountDeseriarizable {
+            address: address.to_owned(),
+            balance: 0,
+            nonce: 0,
+            code: code.to_owned(),
+            storage: storage.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        }
+    }
+
+    fn lint_account(acct: AccountDeseriarizable) -> Vec<String> {
+        let mut linter = Linter {
+            schemas: BTreeMap::new(),
+            problems: vec![],
+        };
+        linter.lint_accounts(Path::new("testcase.json"), &[acct]);
+        linter.problems.into_iter().map(|problem| problem.message).collect()
+    }
+
+    #[test]
+    fn lints_nested_groups_and_shared_source_directories() {
+        let root = std::env::temp_dir().join(format!("evm-tester-lint-{}", std::process::id()));
+        let files = [
+            ("erc20/testcase.json", r#"{"name": "erc20"}"#),
+            ("erc20/lib/util.huff", "#define macro UTIL() = takes(0) returns(0) {}"),
+            ("erc20/t1/state.json", r#"{"id": "t1", "code": "00"}"#),
+            ("erc20/t2/stat.json", r#"{"id": "t2", "code": "00"}"#),
+            ("tokens/erc721/testcase.json", r#"{"name": "erc721"}"#),
+            ("tokens/erc721/mint/state.json", r#"{"id": "mint", "code": "00"}"#),
+            ("empty/notes.txt", ""),
+        ];
+        for (path, text) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+        let problems = lint(&root.display().to_string());
+        let _ = fs::remove_dir_all(&root);
+        let problems: Vec<(String, String)> = problems
+            .unwrap()
+            .into_iter()
+            .map(|problem| (problem.path, problem.message))
+            .collect();
+        let path = |path: &str| root.join(path).display().to_string();
+        assert_eq!(
+            problems,
+            vec![
+                (path("empty"), "directory without a testcase.json (or .yaml, .toml)".to_owned()),
+                (path("erc20/t2/stat.json"), "stray file in a stateful test directory".to_owned()),
+                (path("erc20/t2"), "directory without state.json (or .yaml, .toml)".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_prefixed_and_odd_length_values() {
+        assert!(lint_account(account("0xbeef", "", &[])).is_empty());
+        assert!(lint_account(account("abc", "0x6001", &[])).is_empty());
+        assert!(lint_account(account("01", "", &[("0x1", "abc")])).is_empty());
+        assert!(lint_account(account("01", "", &[("1", "0x0abc")])).is_empty());
+    }
+
+    #[test]
+    fn reports_values_generate_rejects() {
+        let problems = lint_account(account("01", "600", &[]));
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("account 01: invalid hex"), "{}", problems[0]);
+
+        let too_long = "1".repeat(41);
+        assert_eq!(lint_account(account(&too_long, "", &[])).len(), 1);
+        assert_eq!(lint_account(account("01", "", &[("1", &"f".repeat(65))])).len(), 1);
+    }
+}
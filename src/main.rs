@@ -3,19 +3,48 @@ use anyhow::bail;
 use glob::glob;
 use reader::read_stateful;
 use reader::read_stateless;
+use reader::override_accounts;
+use reader::parse_file_type;
+use reader::find_config;
+use reader::ConfigText;
+use reader::read_group_config;
+use reader::read_package_config;
+use reader::stateless_sources;
+use reader::CONFIG_EXTENSIONS;
+use reader::resolve_accounts;
+use std::collections::BTreeSet;
 use std::fs::File;
 use std::io::prelude::*;
-use std::io::BufReader;
 use string_builder::Builder;
 
+mod asm;
 mod core;
 mod executor;
+mod exporter;
+mod importer;
+mod lint;
 mod mover;
+mod opcode;
 mod reader;
+mod record;
+mod scaffold;
 
-use crate::core::{FileType, Output, StateConfig, TestCase, TestGroupConfig, TestCaseSerializable};
+use crate::core::{decode_data, AbortConfig, CaseConfig, SplitConfig, FileType, Input, Output, StateConfig, TestCase, TestGroupConfig, TestCaseSerializable};
 use crate::executor::executor::execute;
-use crate::mover::mover::to_move_test;
+use crate::exporter::forge::write_forge_testgroup;
+use crate::exporter::{charge_fees, runner_post_state};
+use crate::exporter::trie::{logs_hash, state_root};
+use crate::exporter::rust::{write_rust_harness, write_rust_testgroup};
+use crate::exporter::statetest::write_statetest_group;
+use crate::exporter::t8n::write_t8n_group;
+use crate::importer::ethtests::{group_name, read_ethtests};
+use crate::importer::prestate::import_prestate;
+use crate::importer::t8n::compare_t8n;
+use crate::lint::lint;
+use crate::record::record;
+use crate::scaffold::{new_group, new_test};
+use crate::mover::mover::{move_identifier, render_move_testgroup, unique_identifier};
+use crate::mover::package::write_move_package;
 
 #[allow(dead_code)]
 fn write_output(filepath: &str, outputs: &[Output]) -> std::io::Result<()> {
@@ -25,162 +54,515 @@ fn write_output(filepath: &str, outputs: &[Output]) -> std::io::Result<()> {
     Ok(())
 }
 
-fn write_move_testgroup(test_group_name: &str, filepath: &str, testcases: &[TestCase]) -> anyhow::Result<()> {
-    let file = File::create(filepath)?;
-
-    let mut b = Builder::default();
-    b.append("#[test_only]\n");
-    b.append(format!("module pocvm::{}_tests {{\n", test_group_name));
-    b.append("    use std::signer;\n");
-    // b.append("    use std::unit_test;\n");
-    // b.append("    use std::vector;\n");
-    b.append("    use aptos_framework::coin;\n");
-    b.append("    use aptos_framework::aptos_coin::{Self, AptosCoin};\n");
-    b.append("    use aptos_framework::aptos_account;\n");
-    b.append("    use pocvm::vm;\n\n");
-
-    for tc in testcases {
-        let s = to_move_test(tc);
-        b.append(s);
-    }
-    b.append("}\n");
-
-    let text = b.string()?;
-    write!(&file, "{}", text)?;
-    Ok(())
+// writes every module of the group to `<dir>/<module>.move` and returns the paths.
+fn write_move_testgroup(
+    test_group_name: &str,
+    dir: &str,
+    testcases: &[TestCase],
+    template: &str,
+    abort: &AbortConfig,
+    split: &SplitConfig,
+) -> anyhow::Result<Vec<String>> {
+    let mut paths = vec![];
+    for (module, text) in render_move_testgroup(template, test_group_name, testcases, abort, split)? {
+        let filepath = format!("{}/{}.move", dir, module);
+        let file = File::create(&filepath)?;
+        write!(&file, "{}", text)?;
+        paths.push(filepath);
+    }
+    Ok(paths)
 }
 
 fn write_json_testgroup(_test_group_name: &str, filepath: &str, testcases: &[TestCase]) -> anyhow::Result<()> {
     let file = File::create(filepath)?;
     let testcases = Vec::from(testcases);
-    let testcases = testcases.iter().map(|tc| TestCaseSerializable::from(tc));
+    let testcases = testcases.iter().map(TestCaseSerializable::from);
     let testcases: Vec<TestCaseSerializable>  = testcases.collect();
     let text = serde_json::to_string(&testcases)?;
     write!(&file, "{}", text)?;
     Ok(())
 }
 
-#[allow(dead_code)]
+fn write_trace(filepath: &str, testcase: &TestCase) -> anyhow::Result<()> {
+    let file = File::create(filepath)?;
+    let mut b = Builder::default();
+    for step in &testcase.trace {
+        let location = match testcase.source_map.get(&step.pc) {
+            Some(location) if step.target => location.to_string(),
+            _ => "".to_owned(),
+        };
+        b.append(format!(
+            "{:?} {:>5} {:<14} {}\n",
+            step.address,
+            step.pc,
+            opcode::name(step.opcode),
+            location
+        ));
+    }
+    let text = b.string()?;
+    write!(&file, "{}", text)?;
+    Ok(())
+}
+
+// the file name without its extension, e.g. `add.v2` for `add.v2.huff`.
 fn extract_testname(path: &str) -> anyhow::Result<String> {
-    let filename = path.split("/").last().ok_or(anyhow!("invalid file path"))?;
-    let filename = filename.to_owned();
-    let testname = filename.split(".").collect::<Vec<&str>>()[0];
-    Ok(testname.to_owned())
-}
-fn read_test_config(path: &str) -> anyhow::Result<TestGroupConfig> {
-    let json_file = File::open(path)?;
-    let reader = BufReader::new(json_file);
-    let config: TestGroupConfig = serde_json::from_reader(reader)?;
-    Ok(config)
-}
-fn read_state_config(path: &str) -> anyhow::Result<StateConfig> {
-    let json_file = File::open(path)?;
-    let reader = BufReader::new(json_file);
-    let config: StateConfig = serde_json::from_reader(reader)?;
-    Ok(config)
-}
-fn parse_file_type(file_type: &str) -> anyhow::Result<FileType> {
-    if file_type.eq_ignore_ascii_case("huff") {
-        return Ok(FileType::Huff);
-    } else if file_type.eq_ignore_ascii_case("bytecode") {
-        return Ok(FileType::Bytecode);
-    } else if file_type.eq_ignore_ascii_case("sol") {
-        return Ok(FileType::Solidity);
-    }
-    bail!(format!("unknown test file type {:?}", file_type))
+    let stem = std::path::Path::new(path).file_stem().ok_or(anyhow!("invalid file path"))?;
+    Ok(stem.to_string_lossy().to_string())
+}
+fn read_state_config(path: &str, group: &TestGroupConfig) -> anyhow::Result<StateConfig> {
+    ConfigText::read(std::path::Path::new(path), &group.names)?.parse()
+}
+fn to_testcase(id: String, source: &str, input: &Input) -> anyhow::Result<TestCase> {
+    let result = execute(
+        input.value,
+        &input.code,
+        &input.calldata,
+        0,
+        &input.accounts,
+        &input.env,
+        input.snapshot.as_deref(),
+    )?;
+    let testcase = TestCase {
+        funcname: move_identifier(&id),
+        id,
+        source: source.to_owned(),
+        code: result.code,
+        value: result.value,
+        calldata: result.calldata,
+        output: result.output,
+        accounts_input: result.accounts_input,
+        accounts_output: result.accounts_output,
+        pre_state: result.pre_state,
+        result: result.result,
+        source_map: input.source_map.clone(),
+        trace: result.trace,
+        exit_pc: result.exit_pc,
+        env: input.env.clone(),
+        names: input.names.clone(),
+    };
+    if let Some(expect) = &input.expect {
+        expect.check(&testcase.output, &testcase.result, &testcase.accounts_output)?;
+        let root = match expect.state_root {
+            Some(_) => {
+                let post = charge_fees(&input.env, result.post_state, result.used_gas)?;
+                Some(state_root(&runner_post_state(&input.env, post)?))
+            }
+            None => None,
+        };
+        expect.check_roots(root, logs_hash(&result.logs))?;
+    }
+    if !matches!(testcase.result, evm::ExitReason::Succeed(_)) {
+        let location = match testcase.exit_location() {
+            Some(location) => format!(" ({})", location),
+            None => "".to_owned(),
+        };
+        println!(
+            "  {} exited with {:?} at pc {:?}{}",
+            testcase.id, testcase.result, testcase.exit_pc, location
+        );
+    }
+    Ok(testcase)
+}
+
+fn read_stateless_testcase(
+    test_path: &str,
+    file_type: FileType,
+    group: &TestGroupConfig,
+) -> anyhow::Result<TestCase> {
+    let input = read_stateless(test_path, file_type, group)?;
+    let id = extract_testname(test_path)?;
+    to_testcase(id, test_path, &input)
+}
+
+fn read_stateful_testcases(
+    path: &str,
+    group: &TestGroupConfig,
+) -> Vec<(String, anyhow::Result<TestCase>)> {
+    let inputs = match read_stateful_inputs(path, group) {
+        Ok(inputs) => inputs,
+        Err(err) => return vec![(path.to_owned(), Err(err))],
+    };
+    inputs
+        .into_iter()
+        .map(|input| {
+            let test_path = format!("{}:{}", path, input.id);
+            let testcase = to_testcase(input.id.to_owned(), &test_path, &input);
+            (test_path, testcase)
+        })
+        .collect()
+}
+
+fn read_stateful_inputs(path: &str, group: &TestGroupConfig) -> anyhow::Result<Vec<Input>> {
+    let state_config = read_state_config(path, group)?;
+    let input = read_stateful(path, &state_config, group)?;
+    if state_config.cases.is_empty() {
+        return Ok(vec![input]);
+    }
+    let dir = std::path::Path::new(path).parent().unwrap_or(std::path::Path::new("."));
+    state_config
+        .cases
+        .iter()
+        .map(|case| expand_case(&input, case, dir))
+        .collect()
+}
+
+fn expand_case(base: &Input, case: &CaseConfig, dir: &std::path::Path) -> anyhow::Result<Input> {
+    let mut input = base.clone();
+    input.id = format!("{}_{}", base.id, case.id);
+    if let Some(calldata) = &case.calldata {
+        input.calldata = decode_data(calldata)?;
+    }
+    if let Some(value) = case.value {
+        input.value = value;
+    }
+    override_accounts(&mut input.accounts, &resolve_accounts(dir, &case.accounts)?)?;
+    if case.expect.is_some() {
+        input.expect = case.expect.clone();
+    }
+    Ok(input)
+}
+
+#[derive(Default)]
+struct Summary {
+    passed: usize,
+    failed: Vec<(String, anyhow::Error)>,
+}
+
+impl Summary {
+    // adds a test to its group. a test name used twice in a group fails, names that only
+    // collide as move identifiers get a numbered suffix in the order the tests are found.
+    fn record(&mut self, test_path: &str, testcase: anyhow::Result<TestCase>, testcases: &mut Vec<TestCase>) {
+        let testcase = testcase.and_then(|testcase| {
+            if let Some(other) = testcases.iter().find(|other| other.id == testcase.id) {
+                bail!("duplicate test name {:?}, also defined by {}", testcase.id, other.source);
+            }
+            Ok(testcase)
+        });
+        match testcase {
+            Ok(mut testcase) => {
+                let taken: Vec<&str> = testcases.iter().map(|tc| tc.funcname.as_str()).collect();
+                testcase.funcname = unique_identifier(&testcase.funcname, &taken);
+                self.passed += 1;
+                testcases.push(testcase);
+            }
+            Err(err) => {
+                println!("test case failed. {:?}", test_path);
+                self.failed.push((test_path.to_owned(), err));
+            }
+        }
+    }
+
+    fn print(&self) {
+        println!("\n{} test case(s) generated, {} failed.", self.passed, self.failed.len());
+        for (test_path, err) in &self.failed {
+            println!("  FAILED {}: {:#}", test_path, err);
+        }
+    }
+}
+
+// `<dir pattern>/<stem>.<ext>` for every config extension.
+fn config_paths(dir: &str, stem: &str) -> anyhow::Result<Vec<std::path::PathBuf>> {
+    let mut paths = vec![];
+    for ext in CONFIG_EXTENSIONS {
+        for entry in glob(&format!("{}/{}.{}", dir, stem, ext))? {
+            paths.push(entry?);
+        }
+    }
+    Ok(paths)
+}
+
+// the move framework a template is written for. custom templates do not pick one.
+fn template_target(template: &str) -> Option<&'static str> {
+    match template {
+        "" | "aptos" => Some("aptos"),
+        "sui" => Some("sui"),
+        _ => None,
+    }
+}
+
+// the template of a group, `--template` if given. a group written for the other framework fails.
+fn group_template<'a>(args: &'a [String], config: &'a TestGroupConfig) -> anyhow::Result<&'a str> {
+    let Some(template) = option_value(args, "--template") else {
+        return Ok(&config.template);
+    };
+    if !config.template.is_empty() {
+        if let (Some(group_target), Some(target)) = (template_target(&config.template), template_target(template)) {
+            if group_target != target {
+                bail!("template {:?} disagrees with --template {:?}", config.template, template);
+            }
+        }
+    }
+    Ok(template)
+}
+
+// `--package <dir>` also writes the generated modules as a move package, for the framework of
+// the built-in templates the groups were rendered with.
+fn write_package(args: &[String], modules: &[String], targets: &BTreeSet<&str>) -> anyhow::Result<()> {
+    if let Some(dir) = option_value(args, "--package") {
+        let mut config = read_package_config(std::path::Path::new("./resources"))?;
+        if targets.len() > 1 {
+            bail!("groups use both aptos and sui templates, which need separate packages");
+        }
+        if let Some(target) = targets.first() {
+            if config.target.is_empty() {
+                config.target = target.to_string();
+            } else if config.target != *target {
+                bail!("package target {:?} differs from the {} templates of the groups", config.target, target);
+            }
+        }
+        write_move_package(dir, &config, modules)?;
+        println!("move package written. {:?}", dir);
+    }
+    Ok(())
+}
+
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let position = args.iter().position(|arg| arg == name)?;
+    args.get(position + 1).map(|arg| arg.as_str())
 }
 
 fn main() -> anyhow::Result<()> {
-    for entry in glob("./resources/**/testcase.json")? {
-        if let Ok(path) = entry {
-            let mut testcases: Vec<TestCase> = vec![];
-            let path = path.display().to_string();
-            let config = read_test_config(&path)?;
-
-            let huff_path = path.replace("testcase.json", "*.huff");
-            let bc_path = path.replace("testcase.json", "*.bytecode");
-            let stateful_path = path.replace("testcase.json", "*/state.json");
-
-            // stateless huff
-            for entry in glob(&huff_path)? {
-                if let Ok(path) = entry {
-                    let test_path = path.display().to_string();
-
-                    let input = read_stateless(&test_path, FileType::Huff)?;
-                    let funcname = extract_testname(&test_path)?;
-                    let result = execute(input.value, &input.code, &input.calldata, 0, &vec![])?;
-                    let testcase = TestCase {
-                        funcname,
-                        code: result.code,
-                        value: result.value,
-                        calldata: result.calldata,
-                        output: result.output,
-                        accounts_input: result.accounts_input,
-                        accounts_output: result.accounts_output,
-                        result: result.result,
-                    };
-                    testcases.push(testcase);
-                    println!("stateless test case found. {:?}", test_path);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    std::fs::create_dir_all("artifacts/move")?;
+    std::fs::create_dir_all("artifacts/json")?;
+    match args.first().map(|arg| arg.as_str()) {
+        Some("import-ethtests") => import_ethtests(&args[1..]),
+        Some("import-prestate") => {
+            let usage = "usage: import-prestate <dump.json> <group directory> [--id <test id>]";
+            let path = args.get(1).ok_or(anyhow!(usage))?;
+            let group_dir = args.get(2).ok_or(anyhow!(usage))?;
+            let state_path = import_prestate(path, group_dir, option_value(&args, "--id"))?;
+            println!("stateful test case written. {:?}", state_path);
+            Ok(())
+        }
+        Some("record") => {
+            let path = args.get(1).ok_or(anyhow!("usage: record <state.json>"))?;
+            let group_dir = std::path::Path::new(path)
+                .parent()
+                .and_then(|dir| dir.parent())
+                .unwrap_or(std::path::Path::new("."));
+            let group_path = find_config(group_dir, "testcase")
+                .ok_or(anyhow!("{} is not in a test group", path))?;
+            let group = read_group_config(&group_path.display().to_string())?;
+            let inputs = read_stateful_inputs(path, &group)?;
+            let (accounts, slots) = record(path, &group, &inputs)?;
+            println!("{} account(s) and {} slot(s) recorded. {:?}", accounts, slots, path);
+            Ok(())
+        }
+        Some("import-t8n") => {
+            let dir = args.get(1).map(|arg| arg.as_str()).unwrap_or("artifacts/t8n");
+            let mut differing = 0;
+            for entry in glob(&format!("{}/**/expected.json", dir))? {
+                let path = entry?;
+                let test_dir = path.parent().unwrap_or(std::path::Path::new("."));
+                let differences = match compare_t8n(test_dir) {
+                    Ok(differences) => differences,
+                    Err(err) => vec![format!("{:#}", err)],
+                };
+                if differences.is_empty() {
+                    println!("t8n matches. {:?}", test_dir);
+                    continue;
                 }
-            }
-            // stateless bytecode
-            for entry in glob(&bc_path)? {
-                if let Ok(path) = entry {
-                    let test_path = path.display().to_string();
-
-                    let input = read_stateless(&test_path, FileType::Bytecode)?;
-                    let funcname = extract_testname(&test_path)?;
-                    let result = execute(input.value, &input.code, &input.calldata, 0, &vec![])?;
-                    let testcase = TestCase {
-                        funcname,
-                        code: result.code,
-                        value: result.value,
-                        calldata: result.calldata,
-                        output: result.output,
-                        accounts_input: result.accounts_input,
-                        accounts_output: result.accounts_output,
-                        result: result.result,
-                    };
-                    testcases.push(testcase);
-                    println!("stateless test case found. {:?}", test_path);
+                println!("t8n differs. {:?}", test_dir);
+                for difference in &differences {
+                    println!("  {}", difference);
                 }
+                differing += 1;
             }
-            // stateful huff
-            for entry in glob(&stateful_path)? {
-                if let Ok(path) = entry {
-                    let path = path.display().to_string();
-                    let state_config = read_state_config(&path)?;
-                    let test_path = path.replace("state.json", &state_config.filename);
-                    let file_type = parse_file_type(&state_config.filetype)?;
-
-                    let input = read_stateful(&test_path, file_type, &state_config)?;
-                    let result = execute(
-                        input.value,
-                        &input.code,
-                        &input.calldata,
-                        0,
-                        &input.accounts,
-                    )?;
-                    let testcase = TestCase {
-                        funcname: input.id,
-                        code: result.code,
-                        value: result.value,
-                        calldata: result.calldata,
-                        output: result.output,
-                        accounts_input: result.accounts_input,
-                        accounts_output: result.accounts_output,
-                        result: result.result,
-                    };
-                    testcases.push(testcase);
-                    println!("stateful test case found. {:?}", test_path);
+            if differing > 0 {
+                bail!("{} test(s) differ from t8n", differing);
+            }
+            Ok(())
+        }
+        Some("lint") => {
+            let root = args.get(1).map(|arg| arg.as_str()).unwrap_or("./resources");
+            let problems = lint(root)?;
+            for problem in &problems {
+                println!("{}: {}", problem.path, problem.message);
+            }
+            println!("\n{} problem(s) found.", problems.len());
+            if !problems.is_empty() {
+                bail!("lint failed");
+            }
+            Ok(())
+        }
+        Some("new") => {
+            let usage = "usage: new group <directory> [--name <name>]\n       new <stateless|stateful> <group directory> <test id> [--type huff|bytecode|easm]";
+            let kind = args.get(1).ok_or(anyhow!(usage))?;
+            let dir = args.get(2).ok_or(anyhow!(usage))?;
+            let created = match kind.as_str() {
+                "group" => new_group(dir, option_value(&args, "--name"))?,
+                "stateless" | "stateful" => {
+                    let id = args.get(3).ok_or(anyhow!(usage))?;
+                    let filetype = parse_file_type(option_value(&args, "--type").unwrap_or("huff"))?;
+                    new_test(dir, id, &filetype, kind == "stateful")?
                 }
+                _ => bail!(usage),
+            };
+            for path in created {
+                println!("created. {:?}", path);
+            }
+            Ok(())
+        }
+        _ => generate(&args),
+    }
+}
+
+// imports ethereum/tests fixtures from a file or directory. each fixture file becomes one group.
+fn import_ethtests(args: &[String]) -> anyhow::Result<()> {
+    let path = args
+        .first()
+        .ok_or(anyhow!("usage: import-ethtests <file or directory> [--fork <fork>]"))?;
+    let fork = option_value(args, "--fork");
+    let pattern = if std::path::Path::new(path).is_dir() {
+        format!("{}/**/*.json", path)
+    } else {
+        path.to_owned()
+    };
+
+    let template = option_value(args, "--template").unwrap_or_default();
+    let targets = template_target(template).into_iter().collect();
+    let mut summary = Summary::default();
+    let mut modules = vec![];
+    for entry in glob(&pattern)? {
+        let path = entry?.display().to_string();
+        let inputs = match read_ethtests(&path, fork) {
+            Ok(inputs) => inputs,
+            Err(err) => {
+                summary.failed.push((path, err));
+                continue;
+            }
+        };
+        println!("ethereum test file found. {:?}", path);
+
+        let mut testcases: Vec<TestCase> = vec![];
+        for (id, input) in inputs {
+            let test_path = format!("{}:{}", path, id);
+            let testcase =
+                input.and_then(|input| to_testcase(input.id.to_owned(), &test_path, &input));
+            summary.record(&test_path, testcase, &mut testcases);
+        }
+
+        let name = group_name(&path);
+        modules.extend(write_move_testgroup(
+            &name,
+            "artifacts/move",
+            &testcases,
+            template,
+            &AbortConfig::default(),
+            &SplitConfig::default(),
+        )?);
+
+        let json_path = format!("artifacts/json/{}.json", &name);
+        write_json_testgroup(&name, &json_path, &testcases)?;
+    }
+
+    write_package(args, &modules, &targets)?;
+    summary.print();
+    if !summary.failed.is_empty() {
+        bail!("{} test case(s) failed", summary.failed.len());
+    }
+    Ok(())
+}
+
+fn generate(args: &[String]) -> anyhow::Result<()> {
+    let trace = args.iter().any(|arg| arg == "--trace");
+    let statetests = args.iter().any(|arg| arg == "--statetests");
+    let t8n = args.iter().any(|arg| arg == "--t8n");
+    let forge = args.iter().any(|arg| arg == "--forge");
+    let rust = args.iter().any(|arg| arg == "--rust");
+    if rust {
+        write_rust_harness("artifacts/rust")?;
+    }
+    let mut summary = Summary::default();
+    let mut modules = vec![];
+    let mut targets = BTreeSet::new();
+    for path in config_paths("./resources/**", "testcase")? {
+        let mut testcases: Vec<TestCase> = vec![];
+        let group_dir = path.parent().unwrap_or(std::path::Path::new(".")).display().to_string();
+        let path = path.display().to_string();
+        // a group that can not be read fails as a whole, the other groups still run.
+        let group = read_group_config(&path).and_then(|config| {
+            let sources = stateless_sources(&group_dir, &config)?;
+            let template = group_template(args, &config)?.to_owned();
+            Ok((config, sources, template))
+        });
+        let (config, sources, template) = match group {
+            Ok(group) => group,
+            Err(err) => {
+                println!("test group failed. {:?}", path);
+                summary.failed.push((path, err));
+                continue;
+            }
+        };
+
+        // stateless huff, bytecode and easm
+        for (test_path, filetype) in sources {
+            println!("stateless test case found. {:?}", test_path);
+            let testcase = read_stateless_testcase(&test_path, filetype, &config);
+            summary.record(&test_path, testcase, &mut testcases);
+        }
+        // stateful huff
+        for path in config_paths(&format!("{}/*", group_dir), "state")? {
+            let path = path.display().to_string();
+            println!("stateful test case found. {:?}", path);
+            for (test_path, testcase) in read_stateful_testcases(&path, &config) {
+                summary.record(&test_path, testcase, &mut testcases);
+            }
+        }
+        targets.extend(template_target(&template));
+        modules.extend(write_move_testgroup(
+            &config.name,
+            "artifacts/move",
+            &testcases,
+            &template,
+            &config.abort,
+            &config.split,
+        )?);
+
+        let json_path = format!("artifacts/json/{}.json", &config.name);
+        write_json_testgroup(&config.name, &json_path, &testcases)?;
+
+        if trace {
+            let trace_dir = format!("artifacts/trace/{}", &config.name);
+            std::fs::create_dir_all(&trace_dir)?;
+            for tc in &testcases {
+                let trace_path = format!("{}/{}.trace", trace_dir, tc.funcname);
+                write_trace(&trace_path, tc)?;
+            }
+        }
+
+        if statetests {
+            std::fs::create_dir_all("artifacts/statetest")?;
+            let statetest_path = format!("artifacts/statetest/{}.json", &config.name);
+            for (test_path, err) in write_statetest_group(&statetest_path, &testcases)? {
+                println!("  state test skipped. {:?}: {:#}", test_path, err);
+            }
+        }
+
+        if t8n {
+            let t8n_dir = format!("artifacts/t8n/{}", &config.name);
+            for (test_path, err) in write_t8n_group(&t8n_dir, &testcases)? {
+                println!("  t8n export skipped. {:?}: {:#}", test_path, err);
             }
-            let move_path = format!("artifacts/move/{}.move", &config.name);
-            write_move_testgroup(&config.name, &move_path, &testcases)?;
+        }
 
-            let json_path = format!("artifacts/json/{}.json", &config.name);
-            write_json_testgroup(&config.name, &json_path, &testcases)?;
+        if forge {
+            std::fs::create_dir_all("artifacts/forge")?;
+            let forge_path = format!("artifacts/forge/{}.t.sol", &config.name);
+            write_forge_testgroup(&config.name, &forge_path, &testcases)?;
         }
+
+        if rust {
+            let rust_path = format!("artifacts/rust/{}.rs", move_identifier(&config.name));
+            write_rust_testgroup(&config.name, &rust_path, &testcases)?;
+        }
+    }
+
+    write_package(args, &modules, &targets)?;
+    summary.print();
+    if !summary.failed.is_empty() {
+        bail!("{} test case(s) failed", summary.failed.len());
     }
     Ok(())
 }
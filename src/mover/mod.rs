@@ -1 +1,3 @@
-pub mod mover;
\ No newline at end of file
+#[allow(clippy::module_inception)]
+pub mod mover;
+pub mod package;
@@ -1,107 +1,286 @@
-use string_builder::Builder;
-
-use crate::core::{NormalizedAccount, TestCase};
-
-pub(crate) fn to_move_test(testcase: &TestCase) -> String {
-    let mut b = Builder::default();
-
-    b.append("    #[test(admin=@0xff, core_framework=@aptos_framework)]\n");
-    b.append(format!(
-        "    public entry fun test_{}(admin: signer, core_framework: signer) {{\n",
-        testcase.funcname
-    ));
-
-    b.append("        let addr = signer::address_of(&admin);\n");
-    b.append("        let vm_id = vm::init_test(&admin);\n");
-    b.append(
-        "        let (burn_cap, mint_cap) = aptos_coin::initialize_for_test(&core_framework);\n",
-    );
-    b.append("        aptos_account::create_account(addr);\n");
-    b.append(format!(
-        "        coin::deposit(addr, coin::mint({:?}, &mint_cap));\n",
-        testcase.value
-    ));
-    b.append(format!(
-        "        assert!(coin::balance<AptosCoin>(addr) == {:?}, 0);\n",
-        testcase.value
-    ));
-    b.append(format!(
-        "        let code = x\"{}\";\n",
-        hex::encode(&testcase.code)
-    ));
-    b.append(format!(
-        "        let calldata = x\"{}\";\n\n",
-        hex::encode(&testcase.calldata)
-    ));
-
-    deploy_account(&mut b, &testcase.accounts_input);
-
-    b.append("\n        let caller = 0xc000;\n");
-    b.append("        let to = 0xc001;\n");
-    b.append(format!("        let val = {};\n", testcase.value));
-    b.append("        let output = vm::execute(vm_id, caller, to, val, &calldata, &code);\n");
-    b.append(format!(
-        "        assert!(output == x\"{}\", 0);\n\n",
-        hex::encode(&testcase.output)
-    ));
-
-    assert_accounts_output(&mut b, &testcase.accounts_output);
-
-    b.append("\n        coin::destroy_mint_cap<AptosCoin>(mint_cap);\n");
-    b.append("        coin::destroy_burn_cap<AptosCoin>(burn_cap);\n");
-
-    b.append("    }\n\n");
-
-    let s = b.string().unwrap();
-    s
+use anyhow::Context;
+use handlebars::Handlebars;
+use serde::Serialize;
+use std::fs;
+use std::ops::Range;
+
+use crate::core::{exit_name, AbortConfig, NormalizedAccount, SplitConfig, TestCase};
+
+// built-in targets. a group may also name its own handlebars file.
+const APTOS_TEMPLATE: &str = include_str!("../../templates/aptos.hbs");
+const SUI_TEMPLATE: &str = include_str!("../../templates/sui.hbs");
+
+// variables of a move template. a template renders the helper module of the group when
+// `is_helpers` is set, otherwise a test module with `tests`, see `MoveTest`. a blank helper
+// module is not written.
+#[derive(Serialize)]
+struct MoveModule<'a> {
+    // the group.
+    name: &'a str,
+    module: &'a str,
+    helpers: &'a str,
+    is_helpers: bool,
+    tests: &'a [MoveTest],
+}
+
+// hex values are without 0x, numbers are decimal strings.
+#[derive(Serialize)]
+struct MoveTest {
+    name: String,
+    names: Vec<MoveName>,
+    code: String,
+    calldata: String,
+    value: String,
+    output: String,
+    exit: String,
+    // the `#[expected_failure]` attribute when the test does not succeed.
+    expected_failure: Option<String>,
+    accounts_input: Vec<MoveAccount>,
+    accounts_output: Vec<MoveAccount>,
+}
+
+#[derive(Serialize)]
+struct MoveName {
+    name: String,
+    address: String,
+}
+
+#[derive(Serialize)]
+struct MoveAccount {
+    address: String,
+    balance: String,
+    nonce: String,
+    code: String,
+    storage: Vec<MoveSlot>,
+}
+
+#[derive(Serialize)]
+struct MoveSlot {
+    key: String,
+    value: String,
+}
+
+impl From<&NormalizedAccount> for MoveAccount {
+    fn from(acct: &NormalizedAccount) -> Self {
+        MoveAccount {
+            address: hex::encode(acct.address.as_fixed_bytes()),
+            balance: acct.balance.to_string(),
+            nonce: acct.nonce.to_string(),
+            code: hex::encode(&acct.code),
+            storage: acct
+                .storage
+                .iter()
+                .map(|(key, value)| MoveSlot {
+                    key: hex::encode(key.as_fixed_bytes()),
+                    value: hex::encode(value.as_fixed_bytes()),
+                })
+                .collect(),
+        }
+    }
+}
+
+fn to_move_test(testcase: &TestCase, abort: &AbortConfig) -> MoveTest {
+    MoveTest {
+        name: testcase.funcname.to_owned(),
+        names: testcase
+            .names
+            .iter()
+            .map(|(name, address)| MoveName {
+                name: name.to_owned(),
+                address: hex::encode(address),
+            })
+            .collect(),
+        code: hex::encode(&testcase.code),
+        calldata: hex::encode(&testcase.calldata),
+        value: testcase.value.to_string(),
+        output: hex::encode(&testcase.output),
+        exit: exit_name(&testcase.result),
+        expected_failure: abort.expected_failure(&testcase.result),
+        accounts_input: testcase.accounts_input.iter().map(MoveAccount::from).collect(),
+        accounts_output: testcase.accounts_output.iter().map(MoveAccount::from).collect(),
+    }
+}
+
+// a valid move identifier: anything but ascii letters, digits and `_` becomes `_`, and a name
+// not starting with a letter gets a leading `_`.
+pub(crate) fn move_identifier(name: &str) -> String {
+    let identifier: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    match identifier.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => identifier,
+        _ => format!("_{}", identifier),
+    }
+}
+
+// `identifier`, or the first of `identifier_2`, `identifier_3`, .. not in `taken`.
+pub(crate) fn unique_identifier(identifier: &str, taken: &[&str]) -> String {
+    let mut candidate = identifier.to_owned();
+    let mut n = 2;
+    while taken.contains(&candidate.as_str()) {
+        candidate = format!("{}_{}", identifier, n);
+        n += 1;
+    }
+    candidate
+}
+
+// `aptos` (the default), `sui`, or the path of a handlebars template.
+pub(crate) fn load_template(template: &str) -> anyhow::Result<String> {
+    match template {
+        "" | "aptos" => Ok(APTOS_TEMPLATE.to_owned()),
+        "sui" => Ok(SUI_TEMPLATE.to_owned()),
+        path => fs::read_to_string(path).with_context(|| format!("invalid template {:?}", path)),
+    }
 }
 
-fn deploy_account(b: &mut Builder, accounts: &[NormalizedAccount]) {
-    for acct in accounts {
-        let address = acct.address.as_fixed_bytes();
-        let address = hex::encode(address);
-        let code = hex::encode(&acct.code);
-        b.append(format!(
-            "        vm::deploy_account(vm_id, x\"{}\", {:?}, x\"{}\", {:?});\n",
-            &address, acct.balance, &code, acct.nonce
-        ));
+// contiguous ranges of tests within the limits of `split`, given the size of a module without
+// tests and the size each test adds to it. a test over the byte budget gets a module of its own.
+fn shard(split: &SplitConfig, base: usize, sizes: &[usize]) -> Vec<Range<usize>> {
+    let mut shards = vec![];
+    let mut start = 0;
+    let mut bytes = base;
+    for (i, size) in sizes.iter().enumerate() {
+        let full_count = split.max_tests.is_some_and(|max| i - start >= max);
+        let full_bytes = split.max_bytes.is_some_and(|max| bytes + size > max);
+        if i > start && (full_count || full_bytes) {
+            shards.push(start..i);
+            start = i;
+            bytes = base;
+        }
+        bytes += size;
     }
+    shards.push(start..sizes.len());
+    shards
 }
 
-fn assert_accounts_output(b: &mut Builder, accounts: &[NormalizedAccount]) {
-    for acct in accounts {
-        let nonce = acct.nonce.as_u128();
-        let balance = acct.balance.as_u128();
-        let code = hex::encode(&acct.code);
-        let address = acct.address.as_fixed_bytes();
-        let address = hex::encode(address);
-        b.append(format!(
-            "        let nonce = vm::nonce(vm_id, x\"{}\");\n",
-            address
-        ));
-        b.append(format!("        assert!(nonce == {:?}, 0);\n", nonce));
-        b.append(format!(
-            "        let balance = vm::balance(vm_id, x\"{}\");\n",
-            address
-        ));
-        b.append(format!("        assert!(balance == {:?}, 0);\n", balance));
-        b.append(format!(
-            "        let code = vm::code(vm_id, x\"{}\");\n",
-            address
-        ));
-        b.append(format!("        assert!(code == x\"{}\", 0);\n", code));
-
-        for (key, value) in &acct.storage {
-            let key = key.as_fixed_bytes();
-            let key = hex::encode(key);
-            let val = value.as_fixed_bytes();
-            let val = hex::encode(val);
-            b.append(format!(
-                "        let value = vm::storage(vm_id, x\"{}\", x\"{}\");\n",
-                address, key
-            ));
-            b.append(format!("        assert!(value == x\"{}\", 0);\n", val));
+// renders the helper module `<group>_helpers`, unless it renders blank, followed by the test
+// module `<group>_tests`, or `<group>_tests_1`, `<group>_tests_2`, .. when the group exceeds
+// `split`. returns (module name, source) pairs.
+pub(crate) fn render_move_testgroup(
+    template: &str,
+    test_group_name: &str,
+    testcases: &[TestCase],
+    abort: &AbortConfig,
+    split: &SplitConfig,
+) -> anyhow::Result<Vec<(String, String)>> {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(handlebars::no_escape);
+    handlebars.set_strict_mode(true);
+    let template = load_template(template)?;
+    handlebars.register_template_string("group", template)?;
+
+    let name = move_identifier(test_group_name);
+    let helpers = format!("{}_helpers", name);
+    let tests: Vec<MoveTest> = testcases.iter().map(|tc| to_move_test(tc, abort)).collect();
+    let render = |module: &str, is_helpers: bool, tests: &[MoveTest]| {
+        let data = MoveModule {
+            name: &name,
+            module,
+            helpers: &helpers,
+            is_helpers,
+            tests,
+        };
+        handlebars.render("group", &data)
+    };
+
+    let mut modules = vec![];
+    let text = render(&helpers, true, &[])?;
+    if !text.trim().is_empty() {
+        modules.push((helpers.to_owned(), text));
+    }
+    let shards = match split.max_bytes {
+        Some(_) => {
+            // sized with the longest module name.
+            let module = format!("{}_tests_{}", name, tests.len());
+            let base = render(&module, false, &[])?.len();
+            let mut sizes = vec![];
+            for test in &tests {
+                let size = render(&module, false, std::slice::from_ref(test))?.len();
+                sizes.push(size - base);
+            }
+            shard(split, base, &sizes)
+        }
+        None => shard(split, 0, &vec![0; tests.len()]),
+    };
+    if shards.len() == 1 {
+        let module = format!("{}_tests", name);
+        let text = render(&module, false, &tests)?;
+        modules.push((module, text));
+    } else {
+        for (i, range) in shards.into_iter().enumerate() {
+            let module = format!("{}_tests_{}", name, i + 1);
+            let text = render(&module, false, &tests[range])?;
+            modules.push((module, text));
         }
-        b.append("\n");
+    }
+    Ok(modules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn move_identifier_replaces_invalid_chars() {
+        assert_eq!(move_identifier("add"), "add");
+        assert_eq!(move_identifier("add-overflow.1"), "add_overflow_1");
+        assert_eq!(move_identifier("1st"), "_1st");
+        assert_eq!(move_identifier(""), "_");
+    }
+
+    #[test]
+    fn unique_identifier_appends_counter() {
+        assert_eq!(unique_identifier("add", &[]), "add");
+        assert_eq!(unique_identifier("add", &["add"]), "add_2");
+        assert_eq!(unique_identifier("add", &["add", "add_2"]), "add_3");
+    }
+
+    #[test]
+    fn shard_splits_by_count_and_bytes() {
+        let unlimited = SplitConfig::default();
+        assert_eq!(shard(&unlimited, 0, &[0; 3]), vec![0..3]);
+        assert_eq!(shard(&unlimited, 0, &[]), vec![0..0]);
+
+        let by_count = SplitConfig {
+            max_tests: Some(2),
+            max_bytes: None,
+        };
+        assert_eq!(shard(&by_count, 0, &[0; 5]), vec![0..2, 2..4, 4..5]);
+
+        // a test over the budget gets a module of its own.
+        let by_bytes = SplitConfig {
+            max_tests: None,
+            max_bytes: Some(100),
+        };
+        assert_eq!(shard(&by_bytes, 10, &[40, 40, 200, 10]), vec![0..2, 2..3, 3..4]);
+    }
+
+    #[test]
+    fn drops_blank_helper_modules() {
+        let render = |template: &str| {
+            let path = std::env::temp_dir()
+                .join(format!("evm-tester-template-{}.hbs", std::process::id()));
+            fs::write(&path, template).unwrap();
+            let modules = render_move_testgroup(
+                path.to_str().unwrap(),
+                "group",
+                &[],
+                &AbortConfig::default(),
+                &SplitConfig::default(),
+            );
+            fs::remove_file(&path).unwrap();
+            modules.unwrap()
+        };
+        let modules = render("{{#unless is_helpers}}module {{module}} {}{{/unless}}\n");
+        assert_eq!(modules, vec![("group_tests".to_owned(), "module group_tests {}\n".to_owned())]);
+
+        let modules = render("module {{module}} {}");
+        let names: Vec<&str> = modules.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["group_helpers", "group_tests"]);
+
+        let modules = render("{{#if is_helpers}}helpers{{else}}tests{{/if}}");
+        let names: Vec<&str> = modules.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["group_helpers", "group_tests"]);
     }
 }
@@ -0,0 +1,161 @@
+use anyhow::{anyhow, bail};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use crate::core::{MoveDependency, PackageConfig};
+
+fn git(url: &str, rev: &str, subdir: &str) -> MoveDependency {
+    MoveDependency {
+        local: None,
+        git: Some(url.to_owned()),
+        rev: Some(rev.to_owned()),
+        subdir: Some(subdir.to_owned()),
+    }
+}
+
+fn local(path: &str) -> MoveDependency {
+    MoveDependency {
+        local: Some(path.to_owned()),
+        ..MoveDependency::default()
+    }
+}
+
+// aptos release the framework is pinned to.
+const APTOS_FRAMEWORK_REV: &str = "aptos-node-v1.10.0";
+
+// the framework of the target, and the vm under test checked out next to the package.
+fn default_dependencies(target: &str) -> anyhow::Result<BTreeMap<String, MoveDependency>> {
+    let framework = match target {
+        "" | "aptos" => (
+            "AptosFramework",
+            git(
+                "https://github.com/aptos-labs/aptos-core.git",
+                APTOS_FRAMEWORK_REV,
+                "aptos-move/framework/aptos-framework",
+            ),
+        ),
+        "sui" => (
+            "Sui",
+            git(
+                "https://github.com/MystenLabs/sui.git",
+                "framework/testnet",
+                "crates/sui-framework/packages/sui-framework",
+            ),
+        ),
+        _ => bail!("unknown package target {:?}, use aptos or sui", target),
+    };
+    Ok(BTreeMap::from([
+        (framework.0.to_owned(), framework.1),
+        ("Pocvm".to_owned(), local("../pocvm")),
+    ]))
+}
+
+fn write_table(
+    toml: &mut String,
+    name: &str,
+    entries: &BTreeMap<String, String>,
+) -> anyhow::Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    writeln!(toml, "\n[{}]", name)?;
+    for (key, value) in entries {
+        writeln!(toml, "{} = {:?}", key, value)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn move_toml(config: &PackageConfig) -> anyhow::Result<String> {
+    let name = if config.name.is_empty() {
+        "evm_tests"
+    } else {
+        &config.name
+    };
+    let mut addresses = BTreeMap::from([("pocvm".to_owned(), "_".to_owned())]);
+    addresses.extend(config.addresses.clone());
+    let mut dev_addresses = BTreeMap::new();
+    if addresses.get("pocvm").is_some_and(|address| address == "_") {
+        dev_addresses.insert("pocvm".to_owned(), "0xcafe".to_owned());
+    }
+    dev_addresses.extend(config.dev_addresses.clone());
+    let mut dependencies = default_dependencies(&config.target)?;
+    dependencies.extend(config.dependencies.clone());
+
+    let mut toml = String::new();
+    writeln!(toml, "[package]")?;
+    writeln!(toml, "name = {:?}", name)?;
+    writeln!(toml, "version = \"0.0.0\"")?;
+    // the sui templates declare `let mut` bindings, which need the 2024 edition.
+    if config.target == "sui" {
+        writeln!(toml, "edition = \"2024.beta\"")?;
+    }
+    write_table(&mut toml, "addresses", &addresses)?;
+    write_table(&mut toml, "dev-addresses", &dev_addresses)?;
+    writeln!(toml, "\n[dependencies]")?;
+    for (name, dep) in &dependencies {
+        let fields: Vec<String> = [
+            ("local", &dep.local),
+            ("git", &dep.git),
+            ("rev", &dep.rev),
+            ("subdir", &dep.subdir),
+        ]
+        .iter()
+        .filter_map(|(key, value)| value.as_ref().map(|value| format!("{} = {:?}", key, value)))
+        .collect();
+        if fields.is_empty() {
+            bail!("dependency {} has neither local nor git", name);
+        }
+        writeln!(toml, "{} = {{ {} }}", name, fields.join(", "))?;
+    }
+    Ok(toml)
+}
+
+// writes `<dir>/Move.toml` and copies the generated module files to `<dir>/tests/`, replacing
+// the modules of an earlier run. `sources/` is left to the user.
+pub(crate) fn write_move_package(
+    dir: &str,
+    config: &PackageConfig,
+    modules: &[String],
+) -> anyhow::Result<()> {
+    let dir = Path::new(dir);
+    let tests = dir.join("tests");
+    fs::create_dir_all(dir.join("sources"))?;
+    fs::create_dir_all(&tests)?;
+    fs::write(dir.join("Move.toml"), move_toml(config)?)?;
+    for entry in fs::read_dir(&tests)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "move") {
+            fs::remove_file(path)?;
+        }
+    }
+    for path in modules {
+        let path = Path::new(path);
+        let name = path
+            .file_name()
+            .ok_or(anyhow!("invalid module path {}", path.display()))?;
+        fs::copy(path, tests.join(name))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn move_toml_follows_target() {
+        let aptos = move_toml(&PackageConfig::default()).unwrap();
+        assert!(!aptos.contains("edition"));
+        assert!(aptos.contains(&format!("rev = {:?}", APTOS_FRAMEWORK_REV)));
+
+        let config = PackageConfig {
+            target: "sui".to_owned(),
+            ..PackageConfig::default()
+        };
+        let sui = move_toml(&config).unwrap();
+        assert!(sui.contains("version = \"0.0.0\"\nedition = \"2024.beta\"\n"));
+        assert!(sui.contains("Sui = { git = \"https://github.com/MystenLabs/sui.git\""));
+    }
+}
@@ -1,86 +1,741 @@
 use anyhow::*;
+use glob::glob;
 use huff_core::Compiler;
+use huff_utils::prelude::{Artifact, CompilerError, Literal};
+use primitive_types::H160;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 use std::{fs, sync::Arc};
 
-use crate::core::{FileType, Input, StateConfig};
+use crate::asm::assemble;
+use crate::core::{
+    decode_data, parse_address, parse_u256, AccountDeseriarizable, Env, FileType, HuffConfig, Input,
+    PackageConfig, SourceLocation, StateConfig, StatelessConfig, TestGroupConfig,
+};
+
+pub(crate) mod names;
+mod sourcemap;
+mod value;
+
+// extensions of testcase, state and sidecar configs. yaml and toml map onto the same fields as
+// json.
+pub(crate) const CONFIG_EXTENSIONS: [&str; 4] = ["json", "yaml", "yml", "toml"];
+
+// parses a json, yaml or toml config by its extension.
+fn parse_config<T: DeserializeOwned>(extension: &str, data: &str) -> anyhow::Result<T> {
+    let config = match extension {
+        "yaml" | "yml" => serde_yaml::from_str(data)?,
+        "toml" => toml::from_str(data)?,
+        _ => serde_json::from_str(data)?,
+    };
+    Ok(config)
+}
+
+// reads a json, yaml or toml config by its extension. integers above u64 become floats, read
+// configs with balances through `ConfigText`.
+pub(crate) fn read_config_value(path: &Path) -> anyhow::Result<serde_json::Value> {
+    let data = fs::read_to_string(path)?;
+    let extension = path.extension().map(|ext| ext.to_string_lossy().to_string()).unwrap_or_default();
+    let value: value::LossyValue = parse_config(&extension, &data)?;
+    Ok(value.0)
+}
+
+#[derive(Deserialize)]
+struct DeclaredNames {
+    #[serde(default)]
+    names: Vec<String>,
+}
+
+// the text of a config with `${name}` substituted. configs are deserialized from the text, since
+// serde_json::Value turns u128 balances above u64 into floats.
+pub(crate) struct ConfigText {
+    // the file, or the source of header comments, the config was read from.
+    pub origin: String,
+    extension: String,
+    text: String,
+}
+
+impl ConfigText {
+    // reads a config, substituting the names it declares and the `inherited` names of its group.
+    pub(crate) fn read(path: &Path, inherited: &[String]) -> anyhow::Result<ConfigText> {
+        let origin = path.display().to_string();
+        let data = fs::read_to_string(path).with_context(|| format!("invalid {}", origin))?;
+        let extension = path.extension().map(|ext| ext.to_string_lossy().to_string()).unwrap_or_default();
+        ConfigText::new(origin, &extension, &data, inherited)
+    }
+
+    fn new(origin: String, extension: &str, data: &str, inherited: &[String]) -> anyhow::Result<ConfigText> {
+        let declared: DeclaredNames = parse_config(extension, data).with_context(|| format!("invalid {}", origin))?;
+        let text = names::substitute_names_in_text(data, &declared.names, inherited)
+            .with_context(|| format!("invalid {}", origin))?;
+        Ok(ConfigText {
+            origin,
+            extension: extension.to_owned(),
+            text,
+        })
+    }
+
+    // the config as json, e.g. for schema validation. see `read_config_value`.
+    pub(crate) fn value(&self) -> anyhow::Result<serde_json::Value> {
+        let value: value::LossyValue =
+            parse_config(&self.extension, &self.text).with_context(|| format!("invalid {}", self.origin))?;
+        Ok(value.0)
+    }
+
+    pub(crate) fn parse<T: DeserializeOwned>(&self) -> anyhow::Result<T> {
+        parse_config(&self.extension, &self.text).with_context(|| format!("invalid {}", self.origin))
+    }
+}
+
+// `<stem>.json`, `<stem>.yaml`, `<stem>.yml` or `<stem>.toml` in `dir`, whichever exists first.
+pub(crate) fn find_config(dir: &Path, stem: &str) -> Option<PathBuf> {
+    CONFIG_EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", stem, ext)))
+        .find(|path| path.is_file())
+}
+
+// `<root>/package.json` (or .yaml, .toml), the default package config when there is none.
+pub(crate) fn read_package_config(root: &Path) -> anyhow::Result<PackageConfig> {
+    let path = match find_config(root, "package") {
+        Some(path) => path,
+        None => return Ok(PackageConfig::default()),
+    };
+    ConfigText::read(&path, &[])?.parse()
+}
 
 fn strip_non_hex_chars(data: &str) -> String {
-    let original = data.to_owned();
-    original.replace("\n", "")
+    data.lines()
+        .filter(|line| !line.trim_start().starts_with("//"))
+        .flat_map(|line| line.chars())
+        .filter(|c| !c.is_whitespace())
+        .collect()
+}
+
+// reads the stateless test config from `<name>.json` (or .yaml, .toml) next to the source, or
+// else from header comments of the form `// @key value` at the top of the source. keys may be
+// dotted (`env.caller`, `expect.output`), see `header_value` for the values.
+fn read_stateless_config(filepath: &str, group: &TestGroupConfig) -> anyhow::Result<StatelessConfig> {
+    read_stateless_text(filepath, group)?.parse()
+}
+
+// the stateless config of a source with `${name}` substituted. header comments are collected
+// into a json object.
+pub(crate) fn read_stateless_text(filepath: &str, group: &TestGroupConfig) -> anyhow::Result<ConfigText> {
+    let path = Path::new(filepath);
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let sidecar = find_config(path.parent().unwrap_or(Path::new(".")), &stem);
+    if let Some(sidecar) = sidecar {
+        return ConfigText::read(&sidecar, &group.names);
+    }
+
+    let data = fs::read_to_string(filepath)?;
+    // leaves hold the json text of the values, so that numbers keep their precision.
+    let mut header = serde_json::Map::new();
+    for line in data.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let comment = match line.strip_prefix("//") {
+            Some(comment) => comment.trim(),
+            None => break,
+        };
+        let (key, value) = match comment.strip_prefix('@').and_then(|kv| kv.split_once(char::is_whitespace)) {
+            Some((key, value)) => (key, value.trim()),
+            None => continue,
+        };
+        let value = header_value(key, value).with_context(|| format!("{}: invalid @{}", filepath, key))?;
+        let mut object = &mut header;
+        let mut path: Vec<&str> = key.split('.').collect();
+        let last = path.pop().unwrap_or_default();
+        for part in path {
+            object = object
+                .entry(part)
+                .or_insert(serde_json::Value::Object(serde_json::Map::new()))
+                .as_object_mut()
+                .ok_or(anyhow!("{}: @{} conflicts with another header key", filepath, key))?;
+        }
+        object.insert(last.to_owned(), serde_json::Value::String(value));
+    }
+    let origin = format!("header comments of {}", filepath);
+    ConfigText::new(origin, "json", &header_json(&header), &group.names)
+}
+
+// header keys holding integers.
+const HEADER_INTEGER_KEYS: [&str; 9] = [
+    "value",
+    "env.gas_limit",
+    "env.gas_price",
+    "env.block_number",
+    "env.timestamp",
+    "env.difficulty",
+    "env.block_gas_limit",
+    "env.base_fee",
+    "env.chain_id",
+];
+
+// the json text of a header value. arrays, objects, quoted strings and booleans are json,
+// integers are decimal or 0x hex and anything else is a string, e.g. digits only calldata.
+fn header_value(key: &str, value: &str) -> anyhow::Result<String> {
+    if value.starts_with(['[', '{', '"']) || value == "true" || value == "false" {
+        return Ok(value.to_owned());
+    }
+    if HEADER_INTEGER_KEYS.contains(&key) {
+        return Ok(parse_u256(value)?.to_string());
+    }
+    Ok(serde_json::Value::String(value.to_owned()).to_string())
+}
+
+fn header_json(object: &serde_json::Map<String, serde_json::Value>) -> String {
+    let fields: Vec<String> = object
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::Object(object) => header_json(object),
+                serde_json::Value::String(text) => text.to_owned(),
+                value => value.to_string(),
+            };
+            format!("{}: {}", serde_json::Value::String(key.to_owned()), value)
+        })
+        .collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+fn compile_huff(filepath: &str, huff_config: &HuffConfig) -> anyhow::Result<Arc<Artifact>> {
+    let source_path = preprocess_huff(filepath, huff_config)?;
+    let constants = huff_config
+        .constants
+        .iter()
+        .map(|(name, value)| Ok((name.as_str(), to_literal(value)?)))
+        .collect::<anyhow::Result<BTreeMap<&str, Literal>>>()
+        .with_context(|| format!("{}: invalid constant override", filepath))?;
+    let constructor_args = if huff_config.constructor_args.is_empty() {
+        None
+    } else {
+        Some(huff_config.constructor_args.clone())
+    };
+    let constant_overrides = if constants.is_empty() {
+        None
+    } else {
+        Some(constants)
+    };
+
+    let compiler = Compiler::new(
+        Arc::new(vec![source_path.to_owned()]),
+        None,
+        constructor_args,
+        constant_overrides,
+        false,
+        false,
+    );
+    let artifacts = compiler
+        .execute()
+        .map_err(|err| huff_error(filepath, &source_path, &err));
+    if source_path != filepath {
+        let _ = fs::remove_file(&source_path);
+    }
+    let artifacts = artifacts?;
+    let artifact = artifacts
+        .into_iter()
+        .next()
+        .ok_or(anyhow!("{}: huff compiler produced no artifact", filepath))?;
+    Ok(artifact)
+}
+
+// huff_core has no notion of include paths or alternative entry macros, so when either is
+// configured the source is rewritten into a temporary file. lines are rewritten in place, which
+// keeps line numbers but not columns or byte offsets, see `huff_error`.
+fn preprocess_huff(filepath: &str, huff_config: &HuffConfig) -> anyhow::Result<String> {
+    if huff_config.entry_macro.is_none() && huff_config.include_paths.is_empty() {
+        return Ok(filepath.to_owned());
+    }
+
+    let dir = Path::new(filepath).parent().unwrap_or(Path::new("."));
+    let source = fs::read_to_string(filepath)?;
+    let mut lines = vec![];
+    for line in source.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let line = match tokens.as_slice() {
+            ["#include", include, ..] => {
+                let include = include.trim_matches('"');
+                let resolved = resolve_include(dir, include, &huff_config.include_paths)
+                    .ok_or(anyhow!("{}: cannot resolve #include {:?}", filepath, include))?;
+                format!("#include \"{}\"", resolved.display())
+            }
+            ["#define", "macro", name, ..]
+                if huff_config.entry_macro.is_some()
+                    && (*name == "MAIN" || name.starts_with("MAIN(")) =>
+            {
+                line.replacen("MAIN", "__EVM_TESTER_SHADOWED_MAIN", 1)
+            }
+            _ => line.to_owned(),
+        };
+        lines.push(line);
+    }
+    if let Some(entry_macro) = &huff_config.entry_macro {
+        lines.push(format!(
+            "#define macro MAIN() = takes(0) returns(0) {{ {}() }}",
+            entry_macro
+        ));
+    }
+
+    let filename = Path::new(filepath)
+        .file_name()
+        .ok_or(anyhow!("invalid file path"))?
+        .to_string_lossy();
+    let tmp_path = std::env::temp_dir().join(format!("evm-tester-{}-{}", std::process::id(), filename));
+    fs::write(&tmp_path, lines.join("\n"))?;
+    Ok(tmp_path.display().to_string())
+}
+
+fn resolve_include(dir: &Path, include: &str, include_paths: &[String]) -> Option<PathBuf> {
+    let candidates = std::iter::once(dir.to_path_buf())
+        .chain(include_paths.iter().map(|path| dir.join(path)));
+    for candidate in candidates {
+        let path = candidate.join(include);
+        if path.is_file() {
+            return fs::canonicalize(path).ok();
+        }
+    }
+    None
+}
+
+fn to_literal(value: &str) -> anyhow::Result<Literal> {
+    let value = value.trim_start_matches("0x");
+    let value = if value.len() % 2 == 1 {
+        format!("0{}", value)
+    } else {
+        value.to_owned()
+    };
+    let bytes = hex::decode(&value)?;
+    if bytes.len() > 32 {
+        bail!("constant 0x{} does not fit in 32 bytes", value);
+    }
+    let mut literal = [0u8; 32];
+    literal[32 - bytes.len()..].copy_from_slice(&bytes);
+    Ok(literal)
+}
+
+// converts a huff compiler error into an error pointing at the offending source location. must
+// be called before the preprocessed copy of the source is removed.
+fn huff_error(filepath: &str, source_path: &str, err: &CompilerError) -> anyhow::Error {
+    let span = match err {
+        CompilerError::LexicalError(e) => Some(e.span.clone()),
+        CompilerError::ParserError(e) => e.spans.0.first().cloned(),
+        CompilerError::CodegenError(e) => e.span.0.first().cloned(),
+        _ => None,
+    };
+    let span = match span {
+        Some(span) => span,
+        None => return anyhow!("{}: huff compile error: {}", filepath, err),
+    };
+    let span_path = match span.file.as_ref() {
+        Some(file) => file.path.to_owned(),
+        None => source_path.to_owned(),
+    };
+    let source = fs::read_to_string(&span_path).unwrap_or_default();
+    let (line, col) = line_col(&source, span.start);
+    if span_path != source_path || source_path == filepath {
+        return anyhow!(
+            "{}:{}:{}: huff compile error (span {}..{}): {}",
+            span_path, line, col, span.start, span.end, err
+        );
+    }
+
+    // the span points into the preprocessed copy, whose lines correspond one to one to the
+    // original lines, followed by the generated entry macro.
+    let original = fs::read_to_string(filepath).unwrap_or_default();
+    match (original.lines().nth(line - 1), source.lines().nth(line - 1)) {
+        (Some(original_line), Some(rewritten_line)) => anyhow!(
+            "{}:{}:{}: huff compile error: {}",
+            filepath, line, original_col(original_line, rewritten_line, col), err
+        ),
+        _ => anyhow!("{}: huff compile error in the generated entry macro: {}", filepath, err),
+    }
 }
 
-pub(crate) fn read_stateless(filepath: &str, filetype: FileType) -> anyhow::Result<Input> {
-    let bytecode = match filetype {
+// 1-based line and column of the byte `offset` in `source`.
+fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source.as_bytes()[..offset.min(source.len())];
+    let line = before.iter().filter(|b| **b == b'\n').count() + 1;
+    let col = before.iter().rev().take_while(|b| **b != b'\n').count() + 1;
+    (line, col)
+}
+
+// maps a column of a line rewritten by `preprocess_huff` back to the original line. columns in
+// the unchanged prefix or suffix keep their position, columns inside the rewritten part point
+// at its start.
+fn original_col(original: &str, rewritten: &str, col: usize) -> usize {
+    let (original, rewritten) = (original.as_bytes(), rewritten.as_bytes());
+    let prefix = original
+        .iter()
+        .zip(rewritten)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = original[prefix..]
+        .iter()
+        .rev()
+        .zip(rewritten[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let offset = col - 1;
+    if offset < prefix {
+        col
+    } else if offset >= rewritten.len() - suffix {
+        col + original.len() - rewritten.len()
+    } else {
+        prefix + 1
+    }
+}
+
+fn read_code(
+    filepath: &str,
+    filetype: FileType,
+    huff_config: &HuffConfig,
+) -> anyhow::Result<(Vec<u8>, BTreeMap<usize, SourceLocation>)> {
+    match filetype {
         FileType::Huff => {
-            let compiler = Compiler::new(
-                Arc::new(vec![filepath.to_owned()]),
-                None,
-                None,
-                None,
-                false,
-                false,
-            );
-            let res = compiler.execute().unwrap();
-            res[0].runtime.to_owned()
+            let artifact = compile_huff(filepath, huff_config)?;
+            if huff_config.creation {
+                let code = hex::decode(&artifact.bytecode)?;
+                return Ok((code, BTreeMap::new()));
+            }
+            let code = hex::decode(&artifact.runtime)?;
+            let source_map = sourcemap::huff_source_map(filepath, huff_config, &code)
+                .unwrap_or_else(|err| {
+                    println!("warning: no source map for {:?}: {:#}", filepath, err);
+                    BTreeMap::new()
+                });
+            Ok((code, source_map))
         }
         FileType::Solidity => {
             bail!("Solidity not supported.")
         }
         FileType::Bytecode => {
             let data = fs::read_to_string(filepath)?;
-            strip_non_hex_chars(&data)
+            let code = hex::decode(strip_non_hex_chars(&data))?;
+            Ok((code, BTreeMap::new()))
         }
-    };
+        FileType::Easm => {
+            let data = fs::read_to_string(filepath)?;
+            let code = assemble(&data).with_context(|| format!("{}: invalid assembly", filepath))?;
+            Ok((code, BTreeMap::new()))
+        }
+    }
+}
 
-    let code = hex::decode(bytecode)?;
-    let calldata = hex::decode("")?;
+pub(crate) fn parse_file_type(file_type: &str) -> anyhow::Result<FileType> {
+    if file_type.eq_ignore_ascii_case("huff") {
+        return Ok(FileType::Huff);
+    } else if file_type.eq_ignore_ascii_case("bytecode") {
+        return Ok(FileType::Bytecode);
+    } else if file_type.eq_ignore_ascii_case("easm") {
+        return Ok(FileType::Easm);
+    } else if file_type.eq_ignore_ascii_case("sol") {
+        return Ok(FileType::Solidity);
+    }
+    bail!(format!("unknown test file type {:?}", file_type))
+}
+
+// replaces account code referencing a .huff, .bytecode or .easm file (relative to `dir`) with
+// the compiled runtime code.
+pub(crate) fn resolve_accounts(
+    dir: &Path,
+    accounts: &[AccountDeseriarizable],
+) -> anyhow::Result<Vec<AccountDeseriarizable>> {
+    let mut resolved = vec![];
+    for acct in accounts {
+        let mut acct = acct.clone();
+        let extension = Path::new(&acct.code).extension().map(|ext| ext.to_string_lossy().to_string());
+        if let Some(extension) = extension {
+            let filetype = parse_file_type(&extension)?;
+            let filepath = dir.join(&acct.code).display().to_string();
+            let (code, _) = read_code(&filepath, filetype, &HuffConfig::default())?;
+            acct.code = hex::encode(code);
+        }
+        resolved.push(acct);
+    }
+    Ok(resolved)
+}
+
+// replaces accounts with the same address as an override, and appends the others.
+pub(crate) fn override_accounts(
+    accounts: &mut Vec<AccountDeseriarizable>,
+    overrides: &[AccountDeseriarizable],
+) -> anyhow::Result<()> {
+    for acct in overrides {
+        let address = parse_address(&acct.address)?;
+        let base_acct = accounts
+            .iter_mut()
+            .find(|a| parse_address(&a.address).ok() == Some(address));
+        match base_acct {
+            Some(base_acct) => *base_acct = acct.clone(),
+            None => accounts.push(acct.clone()),
+        }
+    }
+    Ok(())
+}
+
+// reads testcase.json (or .yaml, .toml). account code files of the group accounts and fixtures are resolved
+// relative to it.
+pub(crate) fn read_group_config(path: &str) -> anyhow::Result<TestGroupConfig> {
+    let mut config: TestGroupConfig = ConfigText::read(Path::new(path), &[])?.parse()?;
+
+    let dir = Path::new(path).parent().unwrap_or(Path::new("."));
+    config.code_files = group_code_files(dir, &config);
+    config.accounts = resolve_accounts(dir, &config.accounts)?;
+    for fixture in config.fixtures.values_mut() {
+        *fixture = resolve_accounts(dir, std::slice::from_ref(fixture))?.remove(0);
+    }
+    if config.template.ends_with(".hbs") {
+        config.template = dir.join(&config.template).display().to_string();
+    }
+    Ok(config)
+}
+
+// canonical paths of the code files named by the group accounts and fixtures, relative to `dir`.
+pub(crate) fn group_code_files(dir: &Path, config: &TestGroupConfig) -> BTreeSet<PathBuf> {
+    config
+        .accounts
+        .iter()
+        .chain(config.fixtures.values())
+        .map(|acct| Path::new(&acct.code))
+        .filter(|code| code.extension().is_some())
+        .map(|code| {
+            let path = dir.join(code);
+            path.canonicalize().unwrap_or(path)
+        })
+        .collect()
+}
+
+// stateless test sources of a group directory: `.huff`, then `.bytecode`, then `.easm` files,
+// without the code files of the group accounts and fixtures.
+pub(crate) fn stateless_sources(
+    group_dir: &str,
+    config: &TestGroupConfig,
+) -> anyhow::Result<Vec<(String, FileType)>> {
+    let mut sources = vec![];
+    for (ext, filetype) in [
+        ("huff", FileType::Huff),
+        ("bytecode", FileType::Bytecode),
+        ("easm", FileType::Easm),
+    ] {
+        for entry in glob(&format!("{}/*.{}", group_dir, ext))? {
+            let path = entry?;
+            if !config.is_code_file(&path) {
+                sources.push((path.display().to_string(), filetype));
+            }
+        }
+    }
+    Ok(sources)
+}
+
+// group accounts, then the listed fixtures, then the test's own accounts.
+fn group_accounts(
+    group: &TestGroupConfig,
+    fixtures: &[String],
+    dir: &Path,
+    accounts: &[AccountDeseriarizable],
+) -> anyhow::Result<Vec<AccountDeseriarizable>> {
+    let mut merged = group.accounts.clone();
+    for name in fixtures {
+        let fixture = group
+            .fixtures
+            .get(name)
+            .ok_or(anyhow!("fixture {:?} is not defined in the testcase config", name))?;
+        override_accounts(&mut merged, std::slice::from_ref(fixture))?;
+    }
+    override_accounts(&mut merged, &resolve_accounts(dir, accounts)?)?;
+    Ok(merged)
+}
+
+fn named_addresses(group: &TestGroupConfig, names: &[String]) -> BTreeMap<String, H160> {
+    let mut all = group.names.clone();
+    all.extend_from_slice(names);
+    names::named_addresses(&all)
+}
+
+pub(crate) fn read_stateless(
+    filepath: &str,
+    filetype: FileType,
+    group: &TestGroupConfig,
+) -> anyhow::Result<Input> {
+    let (code, source_map) = read_code(filepath, filetype, &HuffConfig::default())?;
+    let config = read_stateless_config(filepath, group)?;
+    let dir = Path::new(filepath).parent().unwrap_or(Path::new("."));
+    let calldata = decode_data(&config.calldata)?;
+    let mut env = Env::default();
+    group.env.apply(&mut env)?;
+    config.env.apply(&mut env)?;
 
     let input = Input {
         id: filepath.to_owned(),
         code,
-        value: 0,
+        value: config.value,
         calldata,
-        accounts: vec![],
+        accounts: group_accounts(group, &config.fixtures, dir, &config.accounts)?,
+        source_map,
+        env,
+        expect: config.expect,
+        names: named_addresses(group, &config.names),
+        snapshot: None,
     };
     Ok(input)
 }
 
 pub(crate) fn read_stateful(
-    filepath: &str,
-    filetype: FileType,
+    state_path: &str,
     state_config: &StateConfig,
+    group: &TestGroupConfig,
 ) -> anyhow::Result<Input> {
-    let bytecode = match filetype {
-        FileType::Huff => {
-            let compiler = Compiler::new(
-                Arc::new(vec![filepath.to_owned()]),
-                None,
-                None,
-                None,
-                false,
-                false,
-            );
-            let res = compiler.execute().unwrap();
-            res[0].runtime.to_owned()
-        }
-        FileType::Solidity => {
-            bail!("Solidity not supported.")
+    let dir = Path::new(state_path).parent().unwrap_or(Path::new("."));
+    let (code, source_map) = match (&state_config.code, &state_config.asm) {
+        (Some(code), _) => (decode_data(code)?, BTreeMap::new()),
+        (None, Some(asm)) => {
+            let code = assemble(asm).with_context(|| format!("{}: invalid asm", state_path))?;
+            (code, BTreeMap::new())
         }
-        FileType::Bytecode => {
-            let data = fs::read_to_string(filepath)?;
-            strip_non_hex_chars(&data)
+        (None, None) => {
+            if state_config.filename.is_empty() {
+                bail!("{}: one of `filename`, `code` or `asm` is required", state_path);
+            }
+            let filepath = dir.join(&state_config.filename).display().to_string();
+            // the file type defaults to the file extension.
+            let filetype = if state_config.filetype.is_empty() {
+                Path::new(&state_config.filename)
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            } else {
+                state_config.filetype.to_owned()
+            };
+            let filetype = parse_file_type(&filetype)?;
+            read_code(&filepath, filetype, &state_config.huff)?
         }
     };
-
-    let code = hex::decode(bytecode)?;
-    let calldata = hex::decode(&state_config.calldata)?;
+    let calldata = decode_data(&state_config.calldata)?;
+    let mut env = Env::default();
+    group.env.apply(&mut env)?;
+    state_config.env.apply(&mut env)?;
 
     let input = Input {
         id: state_config.id.to_owned(),
         code,
         value: state_config.value,
         calldata,
-        accounts: state_config.accounts.clone(),
+        accounts: group_accounts(group, &state_config.fixtures, dir, &state_config.accounts)?,
+        source_map,
+        env,
+        expect: state_config.expect.clone(),
+        names: named_addresses(group, &state_config.names),
+        snapshot: state_config.snapshot.as_ref().map(|path| dir.join(path).display().to_string()),
     };
     Ok(input)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_temp(name: &str, text: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("evm-tester-reader-{}", name));
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn config_text_keeps_u128_balances() {
+        let json = r#"{"names": ["alice"], "address": "${alice}", "balance": 100000000000000000000, "nonce": 0, "code": "", "storage": {}}"#;
+        let yaml = "names: [alice]\naddress: \"${alice}\"\nbalance: 100000000000000000000\nnonce: 0\ncode: \"\"\nstorage: {}\n";
+        for (name, text) in [("account.json", json), ("account.yaml", yaml)] {
+            let path = write_temp(name, text);
+            let account: AccountDeseriarizable = ConfigText::read(&path, &[]).unwrap().parse().unwrap();
+            let _ = fs::remove_file(&path);
+            assert_eq!(account.balance, 100_000_000_000_000_000_000u128, "{}", name);
+            assert_eq!(account.address, hex::encode(names::name_to_address("alice")), "{}", name);
+        }
+    }
+
+    #[test]
+    fn header_comments_keep_u128_values() {
+        let path = write_temp(
+            "header.bytecode",
+            "// @names [\"alice\"]\n// @value 100000000000000000000\n// @calldata 0x${alice}\n// @env.fork london\n6000\n",
+        );
+        let config = read_stateless_config(&path.display().to_string(), &TestGroupConfig::default());
+        let _ = fs::remove_file(&path);
+        let config = config.unwrap();
+        assert_eq!(config.value, 100_000_000_000_000_000_000u128);
+        assert_eq!(config.calldata, format!("0x{}", hex::encode(names::name_to_address("alice"))));
+        assert_eq!(config.env.fork.as_deref(), Some("london"));
+    }
+
+    #[test]
+    fn header_comments_type_values_by_key() {
+        let path = write_temp(
+            "typed.bytecode",
+            "// @calldata 12345678\n// @value 0x10\n// @env.gas_limit 30000\n// @env.caller 0011\n6000\n",
+        );
+        let config = read_stateless_config(&path.display().to_string(), &TestGroupConfig::default());
+        let _ = fs::remove_file(&path);
+        let config = config.unwrap();
+        assert_eq!(config.calldata, "12345678");
+        assert_eq!(config.value, 16);
+        assert_eq!(config.env.gas_limit, Some(30000));
+        assert_eq!(config.env.caller.as_deref(), Some("0011"));
+    }
+
+    #[test]
+    fn fixture_sources_are_not_stateless_tests() {
+        let dir = std::env::temp_dir()
+            .join(format!("evm-tester-reader-group-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let huff = "#define macro MAIN() = takes(0) returns(0) {\n    0x00 0x00 return\n}\n";
+        fs::write(dir.join("token.huff"), huff).unwrap();
+        fs::write(dir.join("add.huff"), huff).unwrap();
+        fs::write(
+            dir.join("testcase.json"),
+            r#"{"name": "group", "fixtures": {"token": {"address": "01", "balance": 0, "nonce": 1, "code": "token.huff", "storage": {}}}}"#,
+        )
+        .unwrap();
+        let group_dir = dir.display().to_string();
+        let config = read_group_config(&dir.join("testcase.json").display().to_string());
+        let sources = config.and_then(|config| stateless_sources(&group_dir, &config));
+        let _ = fs::remove_dir_all(&dir);
+        let sources: Vec<String> = sources.unwrap().into_iter().map(|(path, _)| path).collect();
+        assert_eq!(sources, vec![format!("{}/add.huff", group_dir)]);
+    }
+
+    #[test]
+    fn line_col_counts_from_one() {
+        let source = "#define macro A() = {\n    0x01\n}";
+        assert_eq!(line_col(source, 0), (1, 1));
+        assert_eq!(line_col(source, 26), (2, 5));
+        assert_eq!(line_col(source, 1000), (3, 2));
+    }
+
+    #[test]
+    fn original_col_maps_through_shadowed_main() {
+        let original = "#define macro MAIN() = takes(0) returns(0) { oops }";
+        let rewritten = original.replacen("MAIN", "__EVM_TESTER_SHADOWED_MAIN", 1);
+        let shift = rewritten.len() - original.len();
+        // before the rewrite
+        assert_eq!(original_col(original, &rewritten, 9), 9);
+        // inside the rewrite
+        assert_eq!(original_col(original, &rewritten, 20), 15);
+        // after the rewrite
+        let oops = original.find("oops").unwrap() + 1;
+        assert_eq!(original_col(original, &rewritten, oops + shift), oops);
+    }
+
+    #[test]
+    fn original_col_maps_through_resolved_include() {
+        let original = "#include \"lib.huff\" ";
+        let rewritten = "#include \"/abs/path/lib.huff\" ";
+        assert_eq!(original_col(original, rewritten, 1), 1);
+        assert_eq!(original_col(original, rewritten, rewritten.len()), original.len());
+    }
+}
@@ -0,0 +1,95 @@
+use anyhow::{anyhow, bail};
+use primitive_types::H160;
+use sha3::{Digest, Keccak256};
+use std::collections::BTreeMap;
+
+// deterministic address of a named account, the same as foundry's `makeAddr(name)`: the address
+// of the private key `keccak256(name)`.
+pub(crate) fn name_to_address(name: &str) -> H160 {
+    let key: [u8; 32] = Keccak256::digest(name.as_bytes()).into();
+    // a hash is a valid key unless it is zero or above the curve order.
+    let secret_key = libsecp256k1::SecretKey::parse(&key).expect("keccak256 of a name is a valid secret key");
+    let public_key = libsecp256k1::PublicKey::from_secret_key(&secret_key).serialize();
+    let hash = Keccak256::digest(&public_key[1..]);
+    H160::from_slice(&hash[12..])
+}
+
+pub(crate) fn named_addresses(names: &[String]) -> BTreeMap<String, H160> {
+    names
+        .iter()
+        .map(|name| (name.to_owned(), name_to_address(name)))
+        .collect()
+}
+
+// replaces `${name}` in the text of a config with the hex address (no 0x) of an account declared
+// in its `names` list (`declared`) or in `inherited` (the names of its group). the address is
+// plain hex, so `${name}` belongs inside a string.
+pub(crate) fn substitute_names_in_text(
+    text: &str,
+    declared: &[String],
+    inherited: &[String],
+) -> anyhow::Result<String> {
+    let names: Vec<String> = inherited.iter().chain(declared).cloned().collect();
+    replace(text, &named_addresses(&names))
+}
+
+fn replace(s: &str, names: &BTreeMap<String, H160>) -> anyhow::Result<String> {
+    let mut out = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or(anyhow!("unterminated ${{ in {:?}", s))?;
+        let name = &rest[start + 2..start + end];
+        match names.get(name) {
+            Some(address) => out.push_str(&hex::encode(address)),
+            None => bail!("${{{}}} is not declared in `names`", name),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_to_address_matches_foundry_make_addr() {
+        assert_eq!(
+            format!("{:?}", name_to_address("alice")),
+            "0x328809bc894f92807417d2dad6b7c998c1afdac6"
+        );
+    }
+
+    #[test]
+    fn substitutes_declared_and_inherited_names() {
+        let alice = hex::encode(name_to_address("alice"));
+        let bob = hex::encode(name_to_address("bob"));
+        let text = r#"{"calldata": "0x${alice}${bob}", "storage": {"${alice}": "01"}}"#;
+        let text = substitute_names_in_text(text, &["alice".to_owned()], &["bob".to_owned()]).unwrap();
+        assert_eq!(
+            text,
+            format!(r#"{{"calldata": "0x{}{}", "storage": {{"{}": "01"}}}}"#, alice, bob, alice)
+        );
+    }
+
+    #[test]
+    fn rejects_undeclared_and_unterminated_names() {
+        assert!(substitute_names_in_text(r#""${carol}""#, &[], &[]).is_err());
+        assert!(substitute_names_in_text(r#""${carol""#, &["carol".to_owned()], &[]).is_err());
+    }
+
+    #[test]
+    fn substitutes_text_without_touching_numbers() {
+        let text = r#"{"names": ["alice"], "address": "${alice}", "balance": 100000000000000000000}"#;
+        let text = substitute_names_in_text(text, &["alice".to_owned()], &[]).unwrap();
+        let alice = hex::encode(name_to_address("alice"));
+        assert_eq!(
+            text,
+            format!(r#"{{"names": ["alice"], "address": "{}", "balance": 100000000000000000000}}"#, alice)
+        );
+    }
+}
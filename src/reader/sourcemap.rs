@@ -0,0 +1,310 @@
+use anyhow::{anyhow, bail, Context};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use super::resolve_include;
+use crate::core::{HuffConfig, SourceLocation};
+use crate::opcode;
+
+// recovers a pc -> source line map for huff sources.
+//
+// huff_core's artifacts carry only the compiled code, its codegen does not keep the spans of the
+// bytes it emits. so the entry macro is expanded the same way huff does (macros are inlined,
+// literals become the smallest PUSHn, labels become PUSH2 / JUMPDEST) and the result is checked
+// against the compiled runtime code. constructs that cannot be expanded statically are rejected
+// with an error naming them instead of giving a wrong map:
+// - invocations of macros with arguments and references to macro arguments (`<arg>`),
+// - builtins (`__FUNC_SIG`, `__tablesize`, ...),
+// - constants that are not hex literals (`FREE_STORAGE_POINTER()`),
+// - tables and code tables.
+pub(crate) fn huff_source_map(
+    filepath: &str,
+    huff_config: &HuffConfig,
+    runtime: &[u8],
+) -> anyhow::Result<BTreeMap<usize, SourceLocation>> {
+    let mut contract = Contract {
+        include_paths: huff_config.include_paths.clone(),
+        ..Contract::default()
+    };
+    contract.parse_file(Path::new(filepath), 0)?;
+    for (name, value) in &huff_config.constants {
+        contract.constants.insert(name.to_owned(), Some(value.to_owned()));
+    }
+
+    let entry_macro = huff_config.entry_macro.as_deref().unwrap_or("MAIN");
+    let mut instructions = vec![];
+    contract.expand(entry_macro, &mut instructions, 0)?;
+
+    let mut source_map = BTreeMap::new();
+    let mut pc = 0;
+    for (op, location) in instructions {
+        if runtime.get(pc) != Some(&op) {
+            bail!(
+                "expansion differs from the compiled code at pc {} ({}:{})",
+                pc, location.file, location.line
+            );
+        }
+        source_map.insert(pc, location);
+        pc += 1 + opcode::immediate_size(op);
+    }
+    if pc != runtime.len() {
+        bail!("expansion ends at pc {}, the compiled code at {}", pc, runtime.len());
+    }
+    Ok(source_map)
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    text: String,
+    file: String,
+    line: usize,
+}
+
+impl Token {
+    fn at(&self) -> String {
+        format!("{}:{}", self.file, self.line)
+    }
+}
+
+#[derive(Default)]
+struct Contract {
+    include_paths: Vec<String>,
+    macros: HashMap<String, Vec<Token>>,
+    constants: HashMap<String, Option<String>>,
+}
+
+const MAX_DEPTH: usize = 64;
+const JUMPDEST: u8 = 0x5b;
+const PUSH2: u8 = 0x61;
+
+fn token(tokens: &[Token], i: usize) -> anyhow::Result<&Token> {
+    tokens.get(i).ok_or(anyhow!("unexpected end of source"))
+}
+
+impl Contract {
+    fn parse_file(&mut self, path: &Path, depth: usize) -> anyhow::Result<()> {
+        if depth > MAX_DEPTH {
+            bail!("includes nested deeper than {}", MAX_DEPTH);
+        }
+        let source = fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+        let tokens = tokenize(&source, &path.display().to_string());
+        let dir = path.parent().unwrap_or(Path::new("."));
+
+        let mut i = 0;
+        while i < tokens.len() {
+            match tokens[i].text.as_str() {
+                "#include" => {
+                    let include = token(&tokens, i + 1)?.text.trim_matches('"');
+                    let include = resolve_include(dir, include, &self.include_paths)
+                        .ok_or(anyhow!("{}: cannot resolve #include {:?}", tokens[i].at(), include))?;
+                    self.parse_file(&include, depth + 1)?;
+                    i += 2;
+                }
+                "#define" => match token(&tokens, i + 1)?.text.as_str() {
+                    "macro" => {
+                        let name = token(&tokens, i + 2)?.text.to_owned();
+                        let start = i + tokens[i..]
+                            .iter()
+                            .position(|t| t.text == "{")
+                            .ok_or(anyhow!("{}: macro {} has no body", tokens[i].at(), name))?
+                            + 1;
+                        let mut level = 1;
+                        let mut end = start;
+                        while level > 0 {
+                            match token(&tokens, end)?.text.as_str() {
+                                "{" => level += 1,
+                                "}" => level -= 1,
+                                _ => {}
+                            }
+                            end += 1;
+                        }
+                        self.macros.insert(name, tokens[start..end - 1].to_vec());
+                        i = end;
+                    }
+                    "constant" => {
+                        let name = token(&tokens, i + 2)?.text.to_owned();
+                        let value = token(&tokens, i + 4)?.text.to_owned();
+                        let value = if value.starts_with("0x") { Some(value) } else { None };
+                        self.constants.insert(name, value);
+                        i += 5;
+                    }
+                    // functions, events, tables, ... do not contribute to the entry macro.
+                    _ => {
+                        i += 2;
+                        while i < tokens.len() && !tokens[i].text.starts_with('#') {
+                            i += 1;
+                        }
+                    }
+                },
+                _ => i += 1,
+            }
+        }
+        Ok(())
+    }
+
+    fn expand(&self, name: &str, out: &mut Vec<(u8, SourceLocation)>, depth: usize) -> anyhow::Result<()> {
+        if depth > MAX_DEPTH {
+            bail!("macros nested deeper than {}", MAX_DEPTH);
+        }
+        let body = self.macros.get(name).ok_or(anyhow!("undefined macro {}", name))?;
+        let mut i = 0;
+        while i < body.len() {
+            let token = &body[i];
+            let location = SourceLocation {
+                file: token.file.to_owned(),
+                line: token.line,
+            };
+            let next = body.get(i + 1).map(|t| t.text.as_str());
+            let text = token.text.as_str();
+
+            if text == "[" {
+                let name = &self::token(body, i + 1)?.text;
+                let value = self
+                    .constants
+                    .get(name)
+                    .ok_or(anyhow!("{}: undefined constant {}", token.at(), name))?
+                    .as_ref()
+                    .ok_or(anyhow!("{}: constant {} is not a hex literal", token.at(), name))?;
+                out.push((push_opcode(value)?, location));
+                i += 3;
+            } else if text.starts_with("0x") {
+                out.push((push_opcode(text)?, location));
+                i += 1;
+            } else if text.starts_with("__") {
+                bail!("{}: builtin {}", token.at(), text);
+            } else if text == "<" {
+                let arg = body.get(i + 1).map(|t| t.text.as_str()).unwrap_or_default();
+                bail!("{}: macro argument <{}>", token.at(), arg);
+            } else if next == Some("(") {
+                // only argument-less macro invocations can be expanded statically.
+                if self::token(body, i + 2)?.text != ")" {
+                    bail!("{}: macro {} invoked with arguments", token.at(), text);
+                }
+                self.expand(text, out, depth + 1)?;
+                i += 3;
+            } else if next == Some(":") {
+                out.push((JUMPDEST, location));
+                i += 2;
+            } else if let Some(op) = opcode::from_name(text) {
+                out.push((op, location));
+                // explicit pushes carry their immediate as the next token.
+                if opcode::immediate_size(op) > 0 {
+                    i += 1;
+                }
+                i += 1;
+            } else if text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                // jump label reference.
+                out.push((PUSH2, location));
+                i += 1;
+            } else {
+                bail!("{}: unsupported token {:?}", token.at(), text);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn push_opcode(literal: &str) -> anyhow::Result<u8> {
+    let digits = literal.trim_start_matches("0x").trim_start_matches('0');
+    let size = std::cmp::max(1, digits.len().div_ceil(2));
+    if size > 32 {
+        bail!("literal {} does not fit in 32 bytes", literal);
+    }
+    Ok(0x5f + size as u8)
+}
+
+fn tokenize(source: &str, file: &str) -> Vec<Token> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                i += 1;
+            }
+            i += 2;
+        } else if c == '"' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                i += 1;
+            }
+            i += 1;
+            let text: String = chars[start..i.min(chars.len())].iter().collect();
+            tokens.push(Token { text, file: file.to_owned(), line });
+        } else if c.is_ascii_alphanumeric() || c == '_' || c == '#' || c == '$' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '#' || chars[i] == '$') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            tokens.push(Token { text, file: file.to_owned(), line });
+        } else {
+            tokens.push(Token { text: c.to_string(), file: file.to_owned(), line });
+            i += 1;
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source_map(name: &str, source: &str, runtime: &[u8]) -> anyhow::Result<Vec<(usize, usize)>> {
+        let path = std::env::temp_dir().join(format!("evm-tester-sourcemap-{}.huff", name));
+        fs::write(&path, source)?;
+        let source_map = huff_source_map(&path.display().to_string(), &HuffConfig::default(), runtime);
+        let _ = fs::remove_file(&path);
+        Ok(source_map?.into_iter().map(|(pc, location)| (pc, location.line)).collect())
+    }
+
+    #[test]
+    fn maps_inlined_macros_constants_and_labels() {
+        let source = "#define constant ONE = 0x01\n\
+                      #define macro ADD_ONE() = takes(1) returns(1) {\n    [ONE] add\n}\n\
+                      #define macro MAIN() = takes(0) returns(0) {\n    0x02\n    ADD_ONE()\n    end jump\n    end:\n}\n";
+        // PUSH1 2, PUSH1 1, ADD, PUSH2 8, JUMP, JUMPDEST
+        let runtime = [0x60, 0x02, 0x60, 0x01, 0x01, 0x61, 0x00, 0x08, 0x56, 0x5b];
+        assert_eq!(
+            source_map("inline", source, &runtime).unwrap(),
+            vec![(0, 6), (2, 3), (4, 3), (5, 8), (8, 8), (9, 9)]
+        );
+    }
+
+    #[test]
+    fn names_unsupported_constructs() {
+        let builtin = "#define macro MAIN() = takes(0) returns(0) {\n    __FUNC_SIG(\"f()\")\n}\n";
+        let err = source_map("builtin", builtin, &[]).unwrap_err();
+        assert!(format!("{:#}", err).contains("builtin __FUNC_SIG"), "{:#}", err);
+
+        let pointer = "#define constant SLOT = FREE_STORAGE_POINTER()\n\
+                       #define macro MAIN() = takes(0) returns(0) {\n    [SLOT] sload\n}\n";
+        let err = source_map("pointer", pointer, &[]).unwrap_err();
+        assert!(format!("{:#}", err).contains("constant SLOT is not a hex literal"), "{:#}", err);
+    }
+
+    #[test]
+    fn push_opcode_is_the_smallest_push() {
+        assert_eq!(push_opcode("0x00").unwrap(), 0x60);
+        assert_eq!(push_opcode("0x100").unwrap(), 0x61);
+        assert_eq!(push_opcode(&format!("0x{}", "ff".repeat(32))).unwrap(), 0x7f);
+        assert!(push_opcode(&format!("0x1{}", "00".repeat(32))).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+use anyhow::bail;
+use primitive_types::{H160, H256};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use crate::core::{parse_address, parse_word, Input, StateConfig, TestGroupConfig};
+use crate::executor::executor::{execute, fork_config};
+use crate::reader::ConfigText;
+
+// runs the tests of a state.json and adds the accounts and storage slots they access to its
+// `accounts`, as zero valued pre-state. existing accounts and slots are left as they are.
+// returns the number of accounts and slots added.
+pub(crate) fn record(
+    state_path: &str,
+    group: &TestGroupConfig,
+    inputs: &[Input],
+) -> anyhow::Result<(usize, usize)> {
+    let is_json = Path::new(state_path)
+        .extension()
+        .is_some_and(|ext| ext == "json");
+    if !is_json {
+        bail!("{}: only state.json can be recorded", state_path);
+    }
+    // edited as written, `${name}` addresses included, and compared after substitution.
+    let text = fs::read_to_string(state_path)?;
+    let resolved: StateConfig = ConfigText::read(Path::new(state_path), &group.names)?.parse()?;
+
+    // accounts of the state.json by address, with the slots they already define.
+    let mut known: BTreeMap<H160, (usize, BTreeSet<H256>)> = BTreeMap::new();
+    for (i, acct) in resolved.accounts.iter().enumerate() {
+        let mut slots = BTreeSet::new();
+        for key in acct.storage.keys() {
+            slots.insert(parse_word(key)?);
+        }
+        known.insert(parse_address(&acct.address)?, (i, slots));
+    }
+
+    let mut accessed: BTreeMap<H160, BTreeSet<H256>> = BTreeMap::new();
+    for input in inputs {
+        let mut env = input.env.clone();
+        // access lists are tracked from berlin on.
+        if !fork_config(&env.fork)?.increase_state_access_gas {
+            println!(
+                "  {} recorded with london instead of {}",
+                input.id, env.fork
+            );
+            env.fork = "london".to_owned();
+        }
+        let result = execute(
+            input.value,
+            &input.code,
+            &input.calldata,
+            0,
+            &input.accounts,
+            &env,
+            input.snapshot.as_deref(),
+        )?;
+        for acct in &result.accounts_output {
+            if known.contains_key(&acct.address) {
+                accessed
+                    .entry(acct.address)
+                    .or_default()
+                    .extend(acct.storage.keys());
+                continue;
+            }
+            // the caller is funded by the executor and the test account holds the test code.
+            // group accounts and fixtures belong to testcase.json and snapshot accounts to the
+            // snapshot.
+            let inherited = result
+                .accounts_input
+                .iter()
+                .any(|a| a.address == acct.address);
+            if acct.address == env.caller || acct.address == env.address || inherited {
+                continue;
+            }
+            accessed
+                .entry(acct.address)
+                .or_default()
+                .extend(acct.storage.keys());
+        }
+    }
+
+    let (out, added_accounts, added_slots) = add_accessed(&text, &known, &accessed)?;
+    if added_accounts + added_slots > 0 {
+        fs::write(state_path, out)?;
+    }
+    Ok((added_accounts, added_slots))
+}
+
+// adds the accessed accounts and slots to `accounts` of the state.json text. only `accounts`
+// is edited, the other keys keep their values and order.
+fn add_accessed(
+    text: &str,
+    known: &BTreeMap<H160, (usize, BTreeSet<H256>)>,
+    accessed: &BTreeMap<H160, BTreeSet<H256>>,
+) -> anyhow::Result<(String, usize, usize)> {
+    let mut raw: Value = serde_json::from_str(&quote_big_integers(text))?;
+    let Some(root) = raw.as_object_mut() else {
+        bail!("state.json is not an object");
+    };
+    let Value::Array(accounts) = root.entry("accounts").or_insert_with(|| json!([])) else {
+        bail!("`accounts` is not an array");
+    };
+
+    let mut added_accounts = 0;
+    let mut added_slots = 0;
+    for (address, slots) in accessed {
+        match known.get(address) {
+            Some((i, existing)) => {
+                let storage = accounts
+                    .get_mut(*i)
+                    .and_then(Value::as_object_mut)
+                    .map(|acct| acct.entry("storage").or_insert_with(|| json!({})));
+                let Some(Value::Object(storage)) = storage else {
+                    bail!("account {} has no storage object", hex::encode(address));
+                };
+                for slot in slots.difference(existing) {
+                    storage.insert(short_hex(slot), json!("00"));
+                    added_slots += 1;
+                }
+            }
+            None => {
+                let storage: Map<String, Value> = slots
+                    .iter()
+                    .map(|slot| (short_hex(slot), json!("00")))
+                    .collect();
+                added_slots += storage.len();
+                accounts.push(json!({
+                    "address": hex::encode(address),
+                    "balance": 0,
+                    "nonce": 0,
+                    "code": "",
+                    "storage": storage,
+                }));
+                added_accounts += 1;
+            }
+        }
+    }
+
+    let mut out = vec![];
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut out, formatter);
+    raw.serialize(&mut serializer)?;
+    out.push(b'\n');
+    Ok((unquote_big_integers(&String::from_utf8(out)?), added_accounts, added_slots))
+}
+
+// integers above u64, e.g. large balances, would be read as floats. they are edited as
+// `"\u0000<digits>"` strings and written back as the digits.
+fn quote_big_integers(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.char_indices().peekable();
+    let mut in_string = false;
+    while let Some((i, c)) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next().map(|(_, c)| c)),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        if c != '-' && !c.is_ascii_digit() {
+            in_string = c == '"';
+            out.push(c);
+            continue;
+        }
+        let mut end = i + c.len_utf8();
+        while let Some((j, c)) = chars.next_if(|(_, c)| c.is_ascii_digit() || "+-.eE".contains(*c)) {
+            end = j + c.len_utf8();
+        }
+        let number = &text[i..end];
+        if number.bytes().all(|b| b.is_ascii_digit()) && number.parse::<u64>().is_err() {
+            out.push_str(&format!("\"\\u0000{}\"", number));
+        } else {
+            out.push_str(number);
+        }
+    }
+    out
+}
+
+fn unquote_big_integers(text: &str) -> String {
+    let mut parts = text.split("\"\\u0000");
+    let mut out = parts.next().unwrap_or_default().to_owned();
+    for part in parts {
+        out.push_str(part.replacen('"', "", 1).as_str());
+    }
+    out
+}
+
+// a storage key without leading zero bytes, e.g. `01`.
+fn short_hex(word: &H256) -> String {
+    let bytes = word.as_bytes();
+    let start = bytes
+        .iter()
+        .position(|b| *b != 0)
+        .unwrap_or(bytes.len() - 1);
+    hex::encode(&bytes[start..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_only_the_accounts_of_a_state_json() {
+        let text = r#"{
+    "$schema": "../../state.schema.json",
+    "expect": {
+        "status": "succeed"
+    },
+    "accounts": [
+        {
+            "storage": {
+                "05": "01"
+            },
+            "address": "1000",
+            "balance": 100000000000000000000
+        }
+    ],
+    "env": {
+        "gas_limit": 30000000
+    }
+}
+"#;
+        let address = parse_address("1000").unwrap();
+        let other = parse_address("2000").unwrap();
+        let slot = |n: u8| H256::from_low_u64_be(n as u64);
+        let known = BTreeMap::from([(address, (0, BTreeSet::from([slot(5)])))]);
+        let accessed = BTreeMap::from([
+            (address, BTreeSet::from([slot(5), slot(1)])),
+            (other, BTreeSet::from([slot(2)])),
+        ]);
+        let (out, added_accounts, added_slots) = add_accessed(text, &known, &accessed).unwrap();
+        assert_eq!((added_accounts, added_slots), (1, 2));
+        assert_eq!(
+            out,
+            r#"{
+    "$schema": "../../state.schema.json",
+    "expect": {
+        "status": "succeed"
+    },
+    "accounts": [
+        {
+            "storage": {
+                "05": "01",
+                "01": "00"
+            },
+            "address": "1000",
+            "balance": 100000000000000000000
+        },
+        {
+            "address": "0000000000000000000000000000000000002000",
+            "balance": 0,
+            "nonce": 0,
+            "code": "",
+            "storage": {
+                "02": "00"
+            }
+        }
+    ],
+    "env": {
+        "gas_limit": 30000000
+    }
+}
+"#
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::{anyhow, bail};
+use std::fs;
+use std::path::Path;
+
+use crate::core::{ExpectConfig, FileType, StateConfig, TestGroupConfig};
+use crate::reader::{find_config, read_group_config};
+
+// every template returns the first calldata word.
+const HUFF_TEMPLATE: &str = "#define macro MAIN() = {
+    0x00 calldataload // [word]
+    0x00 mstore       // []
+    0x20 0x00 return
+}
+";
+
+const EASM_TEMPLATE: &str = "0x00 CALLDATALOAD
+0x00 MSTORE
+0x20 0x00 RETURN
+";
+
+const BYTECODE_TEMPLATE: &str = "// 0x00 calldataload 0x00 mstore 0x20 0x00 return
+60003560005260206000f3
+";
+
+const CALLDATA: &str = "000000000000000000000000000000000000000000000000000000000000002a";
+
+fn template(filetype: &FileType) -> anyhow::Result<(&'static str, &'static str)> {
+    match filetype {
+        FileType::Huff => Ok(("huff", HUFF_TEMPLATE)),
+        FileType::Bytecode => Ok(("bytecode", BYTECODE_TEMPLATE)),
+        FileType::Easm => Ok(("easm", EASM_TEMPLATE)),
+        FileType::Solidity => bail!("Solidity not supported."),
+    }
+}
+
+// test ids become move function names.
+fn check_id(id: &str) -> anyhow::Result<()> {
+    let valid = id.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        bail!("invalid test id {:?}, use letters, digits and _", id);
+    }
+    Ok(())
+}
+
+fn write_new(path: &Path, contents: &str) -> anyhow::Result<String> {
+    if path.exists() {
+        bail!("{} already exists", path.display());
+    }
+    fs::write(path, contents)?;
+    Ok(path.display().to_string())
+}
+
+// creates `<dir>/testcase.json`. the group name defaults to the directory name.
+pub(crate) fn new_group(dir: &str, name: Option<&str>) -> anyhow::Result<Vec<String>> {
+    let dir = Path::new(dir);
+    if let Some(path) = find_config(dir, "testcase") {
+        bail!("{} already exists", path.display());
+    }
+    let name = match name {
+        Some(name) => name.to_owned(),
+        None => dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or(anyhow!("invalid group directory {}", dir.display()))?,
+    };
+    check_id(&name)?;
+    let config = TestGroupConfig {
+        name,
+        ..TestGroupConfig::default()
+    };
+    fs::create_dir_all(dir)?;
+    let text = serde_json::to_string_pretty(&config)?;
+    Ok(vec![write_new(&dir.join("testcase.json"), &text)?])
+}
+
+// creates a stateless test `<group>/<id>.<ext>` with header comments, or a stateful test
+// `<group>/<id>/state.json` with its source. both deploy every fixture of the group and expect
+// the output of the template.
+pub(crate) fn new_test(
+    group_dir: &str,
+    id: &str,
+    filetype: &FileType,
+    stateful: bool,
+) -> anyhow::Result<Vec<String>> {
+    check_id(id)?;
+    let group_path = find_config(Path::new(group_dir), "testcase")
+        .ok_or(anyhow!("{} is not a test group (no testcase.json, .yaml or .toml)", group_dir))?;
+    let group = read_group_config(&group_path.display().to_string())?;
+    let fixtures: Vec<String> = group.fixtures.keys().cloned().collect();
+    let (extension, source) = template(filetype)?;
+    let filename = format!("{}.{}", id, extension);
+
+    if !stateful {
+        let mut header = format!("// @calldata 0x{}\n", CALLDATA);
+        if !fixtures.is_empty() {
+            header.push_str(&format!("// @fixtures {}\n", serde_json::to_string(&fixtures)?));
+        }
+        header.push_str(&format!("// @expect.output 0x{}\n", CALLDATA));
+        header.push_str("// @expect.exit Returned\n");
+        let path = Path::new(group_dir).join(&filename);
+        return Ok(vec![write_new(&path, &format!("{}{}", header, source))?]);
+    }
+
+    let test_dir = Path::new(group_dir).join(id);
+    if test_dir.exists() {
+        bail!("{} already exists", test_dir.display());
+    }
+    let config = StateConfig {
+        id: id.to_owned(),
+        names: vec![],
+        filename: filename.to_owned(),
+        filetype: "".to_owned(),
+        code: None,
+        asm: None,
+        value: 0,
+        calldata: CALLDATA.to_owned(),
+        fixtures,
+        accounts: vec![],
+        cases: vec![],
+        snapshot: None,
+        huff: Default::default(),
+        env: Default::default(),
+        expect: Some(ExpectConfig {
+            output: Some(format!("0x{}", CALLDATA)),
+            exit: Some("Returned".to_owned()),
+            ..ExpectConfig::default()
+        }),
+    };
+    fs::create_dir_all(&test_dir)?;
+    let text = serde_json::to_string_pretty(&config)?;
+    Ok(vec![
+        write_new(&test_dir.join("state.json"), &text)?,
+        write_new(&test_dir.join(&filename), source)?,
+    ])
+}
@@ -0,0 +1,170 @@
+// generated by evm-tester. the harness of the tests in the group modules next to this file.
+//
+// the crate including the tests declares this module as `harness` and the implementation under
+// test as `Evm` at its root:
+//
+//     #[path = "../artifacts/rust/harness.rs"]
+//     mod harness;
+//     #[path = "../artifacts/rust/arith.rs"]
+//     mod arith;
+//
+//     type Evm = my_vm::TestEvm;
+//
+// values are hex strings, words may be shorter than 32 bytes and are left padded.
+
+pub type Address = [u8; 20];
+pub type Word = [u8; 32];
+
+pub struct Fixture {
+    pub address: &'static str,
+    pub balance: &'static str,
+    pub nonce: u64,
+    pub code: &'static str,
+    pub storage: &'static [(&'static str, &'static str)],
+}
+
+pub struct Test {
+    pub name: &'static str,
+    pub source: &'static str,
+    // Frontier, Istanbul, Berlin or London.
+    pub fork: &'static str,
+    pub caller: &'static str,
+    pub origin: &'static str,
+    pub address: &'static str,
+    pub value: &'static str,
+    pub calldata: &'static str,
+    pub gas_limit: u64,
+    pub gas_price: &'static str,
+    pub block_number: &'static str,
+    pub block_coinbase: &'static str,
+    pub block_timestamp: &'static str,
+    pub block_difficulty: &'static str,
+    pub block_gas_limit: &'static str,
+    pub block_base_fee_per_gas: &'static str,
+    pub chain_id: &'static str,
+    // every account before the call.
+    pub pre: &'static [Fixture],
+    pub success: bool,
+    pub output: &'static str,
+    // the accounts the call accessed, after the call.
+    pub post: &'static [Fixture],
+}
+
+pub struct Account {
+    pub address: Address,
+    pub balance: Word,
+    pub nonce: u64,
+    pub code: Vec<u8>,
+    pub storage: Vec<(Word, Word)>,
+}
+
+pub struct BlockEnv {
+    pub fork: String,
+    pub gas_price: Word,
+    pub number: Word,
+    pub coinbase: Address,
+    pub timestamp: Word,
+    pub difficulty: Word,
+    pub gas_limit: Word,
+    pub base_fee_per_gas: Word,
+    pub chain_id: Word,
+}
+
+pub struct Message {
+    pub caller: Address,
+    pub origin: Address,
+    pub address: Address,
+    pub value: Word,
+    pub calldata: Vec<u8>,
+    pub gas_limit: u64,
+}
+
+// implemented by the evm under test. `call` runs the message like a transaction, incrementing
+// the nonce of the caller, and returns whether it succeeded and its return data.
+pub trait Evm {
+    fn new(env: &BlockEnv, pre: &[Account]) -> Self;
+    fn call(&mut self, message: &Message) -> (bool, Vec<u8>);
+    fn balance(&self, address: &Address) -> Word;
+    fn nonce(&self, address: &Address) -> u64;
+    fn code(&self, address: &Address) -> Vec<u8>;
+    fn storage(&self, address: &Address, key: &Word) -> Word;
+}
+
+pub fn bytes(hex: &str) -> Vec<u8> {
+    let hex = hex.trim_start_matches("0x");
+    let hex = if hex.len() % 2 == 1 {
+        format!("0{}", hex)
+    } else {
+        hex.to_owned()
+    };
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("invalid hex"))
+        .collect()
+}
+
+pub fn word(hex: &str) -> Word {
+    let bytes = bytes(hex);
+    let mut word = [0u8; 32];
+    word[32 - bytes.len()..].copy_from_slice(&bytes);
+    word
+}
+
+pub fn address(hex: &str) -> Address {
+    let bytes = bytes(hex);
+    let mut address = [0u8; 20];
+    address[20 - bytes.len()..].copy_from_slice(&bytes);
+    address
+}
+
+pub fn account(fixture: &Fixture) -> Account {
+    Account {
+        address: address(fixture.address),
+        balance: word(fixture.balance),
+        nonce: fixture.nonce,
+        code: bytes(fixture.code),
+        storage: fixture
+            .storage
+            .iter()
+            .map(|(key, value)| (word(key), word(value)))
+            .collect(),
+    }
+}
+
+// runs `test` on a new `E` and asserts the result and the accessed accounts.
+pub fn run<E: Evm>(test: &Test) {
+    let env = BlockEnv {
+        fork: test.fork.to_owned(),
+        gas_price: word(test.gas_price),
+        number: word(test.block_number),
+        coinbase: address(test.block_coinbase),
+        timestamp: word(test.block_timestamp),
+        difficulty: word(test.block_difficulty),
+        gas_limit: word(test.block_gas_limit),
+        base_fee_per_gas: word(test.block_base_fee_per_gas),
+        chain_id: word(test.chain_id),
+    };
+    let pre: Vec<Account> = test.pre.iter().map(account).collect();
+    let mut evm = E::new(&env, &pre);
+    let (success, output) = evm.call(&Message {
+        caller: address(test.caller),
+        origin: address(test.origin),
+        address: address(test.address),
+        value: word(test.value),
+        calldata: bytes(test.calldata),
+        gas_limit: test.gas_limit,
+    });
+    let test_at = format!("{} ({})", test.name, test.source);
+    assert_eq!(success, test.success, "{}: success", test_at);
+    assert_eq!(output, bytes(test.output), "{}: output", test_at);
+    for fixture in test.post {
+        let expected = account(fixture);
+        let at = format!("{}: account {}", test_at, fixture.address);
+        assert_eq!(evm.balance(&expected.address), expected.balance, "{} balance", at);
+        assert_eq!(evm.nonce(&expected.address), expected.nonce, "{} nonce", at);
+        assert_eq!(evm.code(&expected.address), expected.code, "{} code", at);
+        for (key, value) in &expected.storage {
+            assert_eq!(evm.storage(&expected.address, key), *value, "{} storage", at);
+        }
+    }
+}